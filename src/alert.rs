@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use hashbrown::HashMap;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{info, warn};
+
+use crate::{config, context, db::update_alert_firing, model::vo::alert::AlertEventVO, util};
+
+/// 告警检查任务, 与数据库同步任务并行运行
+///
+/// 只在规则状态变化时发送回调, 状态会写入数据库, 重启后不会重复触发
+///
+/// Alert check task, runs next to the database sync task
+///
+/// Webhooks are only sent when a rule changes state, the state is stored so restarts do not fire again
+pub async fn alert_task() {
+    info!("Alert task started");
+    let started = util::now();
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(send_task(receiver));
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(config::current().alert_interval)).await;
+        let now = util::now();
+
+        for alert in context!().alerts.get_all() {
+            if !context!().apps.check_app(&alert.app) {
+                continue;
+            }
+            if let Some(api) = &alert.api {
+                if !context!().apis.check_api(&alert.app, api) {
+                    continue;
+                }
+            }
+
+            let (value, firing) = match &alert.kind[..] {
+                "total" => {
+                    let value = match &alert.api {
+                        Some(api) => context!().apis.get_api(&alert.app, api),
+                        None => context!().apis.get_sum(&alert.app),
+                    };
+                    (value, value >= alert.threshold)
+                }
+                kind => {
+                    // 滑动窗口只在内存中, 启动后需要等待一个完整的窗口
+                    //
+                    // Sliding windows only live in memory, wait for one full window after starting
+                    if now - started < alert.window {
+                        continue;
+                    }
+                    let value =
+                        context!()
+                            .windows
+                            .sum(&alert.app, alert.api.as_deref(), now, alert.window);
+                    match kind {
+                        "idle" => (value, value == 0),
+                        _ => (value, value > alert.threshold),
+                    }
+                }
+            };
+            if firing == alert.firing {
+                continue;
+            }
+
+            // 检查期间规则可能被修改或删除, 此时丢弃这次结果
+            //
+            // The rule may be changed or removed during the check, the result is dropped then
+            let alert = match context!().alerts.set_firing(&alert, firing) {
+                Some(alert) => alert,
+                None => continue,
+            };
+            match update_alert_firing(&alert).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => warn!("Failed to save alert {} state: {}", alert.id, e),
+            }
+            info!("Alert {} {}: {}", alert.id, alert.kind, firing);
+
+            let webhook = match alert
+                .webhook
+                .clone()
+                .or_else(|| config::current().alert_webhook.clone())
+            {
+                Some(webhook) => webhook,
+                None => continue,
+            };
+            let state = match firing {
+                true => "firing",
+                false => "resolved",
+            };
+            let event = AlertEventVO {
+                id: format!("{}-{}-{}", alert.id, now, state),
+                state,
+                alert,
+                value,
+                time: now,
+            };
+            let _ = sender.send((webhook, event));
+        }
+    }
+}
+
+/// 分发告警回调, 每个回调地址使用单独的任务, 无法访问的地址不会阻塞其他地址
+///
+/// Dispatch alert webhooks, every webhook url has its own task so an unreachable one does not block the others
+async fn send_task(mut receiver: UnboundedReceiver<(String, AlertEventVO)>) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let mut senders: HashMap<String, UnboundedSender<AlertEventVO>> = HashMap::new();
+
+    while let Some((webhook, event)) = receiver.recv().await {
+        let sender = senders.entry(webhook.to_owned()).or_insert_with(|| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(send_webhook(client.clone(), webhook, receiver));
+            sender
+        });
+        let _ = sender.send(event);
+    }
+}
+
+/// 依次发送同一地址的告警回调, 保持事件顺序, 失败时按指数退避重试
+///
+/// Send the alert webhooks of one url in order, retrying with exponential backoff on failure
+async fn send_webhook(
+    client: reqwest::Client,
+    webhook: String,
+    mut receiver: UnboundedReceiver<AlertEventVO>,
+) {
+    while let Some(event) = receiver.recv().await {
+        let retries = config::current().alert_retries;
+        for attempt in 0..=retries {
+            let result = client
+                .post(&webhook)
+                .json(&event)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+            match result {
+                Ok(_) => break,
+                Err(e) => {
+                    warn!("Alert webhook {} failed: {}", event.id, e);
+                    if attempt < retries {
+                        tokio::time::sleep(Duration::from_secs(1 << attempt.min(6))).await;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use parking_lot::RwLock;
+
+use crate::model::Alert;
+
+/// 记录所有告警规则
+///
+/// Record all alert rules
+pub struct AllAlert {
+    map: Arc<RwLock<HashMap<i64, Alert>>>,
+}
+
+impl AllAlert {
+    pub fn new(map: HashMap<i64, Alert>) -> Self {
+        Self {
+            map: Arc::new(RwLock::new(map)),
+        }
+    }
+
+    /// 添加或替换告警规则
+    ///
+    /// Add or replace an alert rule
+    pub fn set(&self, alert: Alert) {
+        self.map.write().insert(alert.id, alert);
+    }
+
+    /// 获取告警规则
+    ///
+    /// Get an alert rule
+    pub fn get(&self, id: i64) -> Option<Alert> {
+        self.map.read().get(&id).cloned()
+    }
+
+    /// 获取所有告警规则, 按 id 排序
+    ///
+    /// Get all alert rules sorted by id
+    pub fn get_all(&self) -> Vec<Alert> {
+        let mut alerts: Vec<Alert> = self.map.read().values().cloned().collect();
+        alerts.sort_by_key(|alert| alert.id);
+        alerts
+    }
+
+    /// 删除告警规则
+    ///
+    /// Remove an alert rule
+    pub fn remove(&self, id: i64) -> Option<Alert> {
+        self.map.write().remove(&id)
+    }
+
+    /// 更新告警状态, 规则在检查后被修改或删除时返回 None
+    ///
+    /// Update the firing state, returns None if the rule was changed or removed after the check
+    pub fn set_firing(&self, checked: &Alert, firing: bool) -> Option<Alert> {
+        let mut map = self.map.write();
+        let alert = map.get_mut(&checked.id)?;
+        if alert != checked {
+            return None;
+        }
+        alert.firing = firing;
+        Some(alert.clone())
+    }
+}
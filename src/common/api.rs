@@ -32,6 +32,24 @@ impl AllApi {
         count.fetch_add(1, Ordering::Relaxed)
     }
 
+    /// 将 api 的调用次数增加指定值
+    ///
+    /// Add the given value to the number of calls to the api
+    pub fn add(&self, app: &str, api: &str, count: i64) -> i64 {
+        let count_api = { self.map.read().get(app).unwrap().clone() };
+        let total = { count_api.read().get(api).unwrap().clone() };
+        total.fetch_add(count, Ordering::Relaxed) + count
+    }
+
+    /// 设置 api 的调用次数
+    ///
+    /// Set the number of calls to the api
+    pub fn set(&self, app: &str, api: &str, count: i64) {
+        let count_api = { self.map.read().get(app).unwrap().clone() };
+        let total = { count_api.read().get(api).unwrap().clone() };
+        total.store(count, Ordering::Relaxed);
+    }
+
     /// 添加一个 api
     ///
     /// Add a new api
@@ -93,6 +111,13 @@ impl AllApi {
             .map(|(api, count)| (api.to_owned(), count.load(Ordering::Relaxed)))
             .collect()
     }
+
+    /// 获取所有 app 的 api 数量
+    ///
+    /// Get the number of apis of all apps
+    pub fn count(&self) -> usize {
+        self.map.read().values().map(|apis| apis.read().len()).sum()
+    }
 }
 
 /// 记录需要新增的 api
@@ -134,6 +159,13 @@ impl WaitApi {
         apis.write().insert(api.to_owned());
     }
 
+    /// 获取等待新增的 api 数量
+    ///
+    /// Get the number of apis waiting to be added
+    pub fn pending(&self) -> usize {
+        self.map.read().values().map(|apis| apis.read().len()).sum()
+    }
+
     /// 获取所有需要添加的 api
     ///
     /// Get all the apis that need to be added and clear the map
@@ -23,6 +23,13 @@ impl AllApp {
     pub fn check_app(&self, app: &str) -> bool {
         self.set.read().contains(app)
     }
+
+    /// 获取 app 的数量
+    ///
+    /// Get the number of apps
+    pub fn count(&self) -> usize {
+        self.set.read().len()
+    }
 }
 
 /// 记录所有需要新增的 app
@@ -52,4 +59,11 @@ impl WaitApp {
     pub fn get_all(&self) -> HashSet<String> {
         std::mem::take(&mut *self.set.write())
     }
+
+    /// 获取等待新增的 app 数量
+    ///
+    /// Get the number of apps waiting to be added
+    pub fn pending(&self) -> usize {
+        self.set.read().len()
+    }
 }
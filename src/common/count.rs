@@ -0,0 +1,106 @@
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc,
+};
+
+use hashbrown::{HashMap, HashSet};
+use parking_lot::RwLock;
+
+/// 以任意 key 记录的计数, 如每篇文章的点赞数
+///
+/// Counters keyed by arbitrary keys, such as the likes of every article
+pub struct AllCount {
+    map: Arc<RwLock<HashMap<String, Arc<AtomicI64>>>>,
+    /// 等待写入数据库的 key
+    ///
+    /// Keys waiting to be written to the database
+    wait: Arc<RwLock<HashSet<String>>>,
+}
+
+impl AllCount {
+    pub fn new(map: HashMap<String, Arc<AtomicI64>>) -> Self {
+        Self {
+            map: Arc::new(RwLock::new(map)),
+            wait: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<i64> {
+        self.map
+            .read()
+            .get(key)
+            .map(|count| count.load(Ordering::Relaxed))
+    }
+
+    fn get_or_insert(&self, key: &str) -> Arc<AtomicI64> {
+        if let Some(count) = self.map.read().get(key) {
+            return count.clone();
+        }
+        self.map
+            .write()
+            .entry(key.to_owned())
+            .or_insert_with(|| Arc::new(AtomicI64::new(0)))
+            .clone()
+    }
+
+    fn mark(&self, key: &str) {
+        if !self.wait.read().contains(key) {
+            self.wait.write().insert(key.to_owned());
+        }
+    }
+
+    /// 累加, 不存在时从 0 开始, 返回累加后的值, 溢出时不修改并返回 None
+    ///
+    /// Add to the counter, starting from 0 when missing, returns the value after adding,
+    /// nothing is changed and None is returned on overflow
+    pub fn add(&self, key: &str, delta: i64) -> Option<i64> {
+        let value = self
+            .get_or_insert(key)
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |value| {
+                value.checked_add(delta)
+            })
+            .ok()?
+            + delta;
+        self.mark(key);
+        Some(value)
+    }
+
+    pub fn set(&self, key: &str, value: i64) {
+        self.get_or_insert(key).store(value, Ordering::Relaxed);
+        self.mark(key);
+    }
+
+    /// 获取等待写入的计数数量
+    ///
+    /// Get the number of counters waiting to be written
+    pub fn pending(&self) -> usize {
+        self.wait.read().len()
+    }
+
+    /// 获取所有需要写入的计数并清空
+    ///
+    /// Get all counters that need to be written and clear them
+    pub fn get_wait(&self) -> Vec<(String, i64)> {
+        std::mem::take(&mut *self.wait.write())
+            .into_iter()
+            .filter_map(|key| self.get(&key).map(|count| (key, count)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_overflow() {
+        let counts = AllCount::new(HashMap::new());
+        assert_eq!(counts.add("key", 2), Some(2));
+        assert_eq!(counts.add("key", i64::MAX), None);
+        assert_eq!(counts.add("key", -3), Some(-1));
+        counts.set("key", i64::MIN);
+        assert_eq!(counts.add("key", -1), None);
+        assert_eq!(counts.get("key"), Some(i64::MIN));
+        assert_eq!(counts.get_wait(), vec![("key".to_owned(), i64::MIN)]);
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use hashbrown::{HashMap, HashSet};
+use parking_lot::RwLock;
+
+use crate::{common::visitor, model::Flag};
+
+/// 功能开关的灰度规则
+///
+/// Rollout rules of a feature flag
+#[derive(Debug)]
+pub struct Rule {
+    pub app: String,
+    /// 开启的用户比例 (0 - 100)
+    ///
+    /// Percentage of users the flag is on for (0 - 100)
+    pub percentage: f64,
+    pub allow: HashSet<String>,
+    pub deny: HashSet<String>,
+}
+
+impl Rule {
+    pub fn from_flag(flag: &Flag) -> Option<Self> {
+        Some(Self {
+            app: flag.app.to_owned(),
+            percentage: flag.percentage,
+            allow: serde_json::from_str(&flag.allow).ok()?,
+            deny: serde_json::from_str(&flag.deny).ok()?,
+        })
+    }
+
+    /// 对用户求值, 禁止名单优先于允许名单, 其余用户按一致性哈希分桶
+    ///
+    /// Evaluate for a user, the deny list wins over the allow list,
+    /// other users are bucketed by a consistent hash
+    pub fn evaluate(&self, key: &str, user: &str) -> bool {
+        if self.deny.contains(user) {
+            return false;
+        }
+        if self.allow.contains(user) {
+            return true;
+        }
+        let hash = visitor::hash(format!("{}:{}", key, user).as_bytes());
+        ((hash % 10000) as f64) < self.percentage * 100.0
+    }
+}
+
+/// 记录所有功能开关的规则
+///
+/// Record the rules of all feature flags
+#[derive(Default)]
+pub struct AllFlag {
+    map: Arc<RwLock<HashMap<String, Arc<Rule>>>>,
+}
+
+impl AllFlag {
+    pub fn set(&self, key: &str, rule: Rule) {
+        self.map.write().insert(key.to_owned(), Arc::new(rule));
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<Rule>> {
+        self.map.read().get(key).cloned()
+    }
+
+    pub fn remove(&self, key: &str) -> bool {
+        self.map.write().remove(key).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(percentage: f64) -> Rule {
+        Rule {
+            app: "app".to_owned(),
+            percentage,
+            allow: HashSet::from(["vip".to_owned(), "both".to_owned()]),
+            deny: HashSet::from(["banned".to_owned(), "both".to_owned()]),
+        }
+    }
+
+    fn enabled(rule: &Rule, key: &str) -> Vec<usize> {
+        (0..10000)
+            .filter(|i| rule.evaluate(key, &format!("user{i}")))
+            .collect()
+    }
+
+    #[test]
+    fn lists() {
+        assert!(rule(0.0).evaluate("flag", "vip"));
+        assert!(!rule(100.0).evaluate("flag", "banned"));
+        assert!(!rule(100.0).evaluate("flag", "both"));
+    }
+
+    #[test]
+    fn bucketing() {
+        assert!(enabled(&rule(0.0), "flag").is_empty());
+        assert_eq!(enabled(&rule(100.0), "flag").len(), 10000);
+
+        let half = enabled(&rule(50.0), "flag");
+        assert!((4700..5300).contains(&half.len()), "{}", half.len());
+        assert!((50..150).contains(&enabled(&rule(1.0), "flag").len()));
+
+        // 比例增加时已开启的用户保持开启
+        //
+        // Users already on stay on when the percentage grows
+        let fifth = enabled(&rule(20.0), "flag");
+        assert!(fifth.iter().all(|user| half.contains(user)));
+
+        // 同一用户的结果稳定, 不同开关独立分桶
+        //
+        // The result is stable for a user, and different flags are bucketed independently
+        assert_eq!(enabled(&rule(50.0), "flag"), half);
+        let other = enabled(&rule(50.0), "other");
+        let both = other.iter().filter(|user| half.contains(user)).count();
+        assert!((2200..2800).contains(&both), "{both}");
+    }
+}
@@ -0,0 +1,267 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+
+const MINUTE: i64 = 60;
+
+/// 时间戳所在的分钟, 为该分钟开始的时间戳
+///
+/// The minute of the timestamp, as the timestamp of its start
+pub fn minute(time: i64) -> i64 {
+    time - time.rem_euclid(MINUTE)
+}
+
+/// 一分钟内的采样统计
+///
+/// Sample statistics of a minute
+#[derive(Debug, Clone, Copy)]
+pub struct Bucket {
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    pub count: i64,
+    pub last: f64,
+}
+
+impl Bucket {
+    pub fn new(value: f64) -> Self {
+        Self {
+            min: value,
+            max: value,
+            sum: value,
+            count: 1,
+            last: value,
+        }
+    }
+
+    /// 合并较晚的统计
+    ///
+    /// Merge later statistics
+    pub fn merge(&mut self, other: &Bucket) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.count += other.count;
+        self.last = other.last;
+    }
+}
+
+struct State {
+    value: f64,
+    /// 等待写入数据库的采样
+    ///
+    /// Samples waiting to be written to the database
+    pending: BTreeMap<i64, Bucket>,
+    /// 当前值是否有未写入数据库的改动
+    ///
+    /// Whether the current value has changes not yet written to the database
+    changed: bool,
+    /// 最近一个有采样的分钟
+    ///
+    /// The latest minute with a sample
+    sampled: i64,
+}
+
+impl State {
+    /// `sampled` 之后的分钟会以 `value` 补全采样
+    ///
+    /// Minutes after `sampled` are sampled with `value`
+    fn new(value: f64, sampled: i64) -> Self {
+        Self {
+            value,
+            pending: BTreeMap::new(),
+            changed: false,
+            sampled,
+        }
+    }
+
+    fn sample(&mut self, time: i64) {
+        let bucket = Bucket::new(self.value);
+        self.pending
+            .entry(time)
+            .and_modify(|b| b.merge(&bucket))
+            .or_insert(bucket);
+        self.sampled = time;
+    }
+
+    /// 以当前值补全到 `now` 为止没有采样的分钟
+    ///
+    /// Sample the minutes without samples up to `now` with the current value
+    fn carry(&mut self, now: i64) {
+        let mut time = self.sampled + MINUTE;
+        while time <= minute(now) {
+            self.sample(time);
+            time += MINUTE;
+        }
+    }
+}
+
+type GaugeApi = Arc<RwLock<HashMap<String, Arc<Mutex<State>>>>>;
+
+/// 记录所有 api 的仪表值
+///
+/// Record the gauges of all apis
+#[derive(Default)]
+pub struct AllGauge {
+    map: Arc<RwLock<HashMap<String, GaugeApi>>>,
+}
+
+/// 写入数据库的当前值与采样
+///
+/// Current values and samples to write to the database
+pub type Dirty = (
+    Vec<(String, String, f64)>,
+    Vec<(String, String, i64, Bucket)>,
+);
+
+impl AllGauge {
+    fn get_state(&self, app: &str, api: &str) -> Option<Arc<Mutex<State>>> {
+        self.map.read().get(app)?.read().get(api).cloned()
+    }
+
+    /// 设置当前值, 用于从数据库恢复
+    ///
+    /// Set the current value, used to restore from the database
+    pub fn restore(&self, app: &str, api: &str, value: f64, now: i64) {
+        let apis = self.map.write().entry(app.to_owned()).or_default().clone();
+        apis.write().insert(
+            api.to_owned(),
+            Arc::new(Mutex::new(State::new(value, minute(now) - MINUTE))),
+        );
+    }
+
+    pub fn get(&self, app: &str, api: &str) -> Option<f64> {
+        Some(self.get_state(app, api)?.lock().value)
+    }
+
+    /// 以当前值计算新值并记录采样, 返回新值, 新值不是有限数时不修改并返回 None.
+    /// 仪表不存在时当前值为 0
+    ///
+    /// Compute the new value from the current one and record a sample, returns the new value,
+    /// nothing is changed and None is returned if it is not finite.
+    /// The current value is 0 when the gauge does not exist
+    pub fn update(
+        &self,
+        app: &str,
+        api: &str,
+        now: i64,
+        f: impl FnOnce(f64) -> f64,
+    ) -> Option<f64> {
+        let state = match self.get_state(app, api) {
+            Some(state) => state,
+            None => {
+                let apis = self.map.write().entry(app.to_owned()).or_default().clone();
+                let state = apis
+                    .write()
+                    .entry(api.to_owned())
+                    .or_insert_with(|| Arc::new(Mutex::new(State::new(0.0, minute(now)))))
+                    .clone();
+                state
+            }
+        };
+        let mut state = state.lock();
+        let value = f(state.value);
+        if !value.is_finite() {
+            return None;
+        }
+        state.carry(now);
+        state.value = value;
+        state.changed = true;
+        state.sample(minute(now));
+        Some(value)
+    }
+
+    /// 获取等待写入的 `from` 至 `to` 分钟内的采样
+    ///
+    /// Get the pending samples from `from` to `to` minutes
+    pub fn get_pending(&self, app: &str, api: &str, from: i64, to: i64) -> Vec<(i64, Bucket)> {
+        match self.get_state(app, api) {
+            Some(state) => state
+                .lock()
+                .pending
+                .range(from..=to)
+                .map(|(time, bucket)| (*time, *bucket))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// 获取所有改动的当前值与等待写入的采样, 并清空.
+    /// 没有修改的分钟以当前值作为采样, 使每分钟都有历史
+    ///
+    /// Get all changed current values and pending samples, and clear them.
+    /// Minutes without changes are sampled with the current value, so every minute has history
+    pub fn get_dirty(&self, now: i64) -> Dirty {
+        let (mut values, mut samples) = (vec![], vec![]);
+        let apps: Vec<(String, GaugeApi)> = self
+            .map
+            .read()
+            .iter()
+            .map(|(app, apis)| (app.to_owned(), apis.clone()))
+            .collect();
+        for (app, apis) in apps {
+            let apis: Vec<(String, Arc<Mutex<State>>)> = apis
+                .read()
+                .iter()
+                .map(|(api, state)| (api.to_owned(), state.clone()))
+                .collect();
+            for (api, state) in apis {
+                let mut state = state.lock();
+                state.carry(now);
+                if state.changed {
+                    state.changed = false;
+                    values.push((app.to_owned(), api.to_owned(), state.value));
+                }
+                for (time, bucket) in std::mem::take(&mut state.pending) {
+                    samples.push((app.to_owned(), api.to_owned(), time, bucket));
+                }
+            }
+        }
+        (values, samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carry() {
+        let gauges = AllGauge::default();
+        let start = 1_700_000_040;
+        gauges.restore("app", "api", 5.0, start);
+        assert_eq!(
+            gauges.update("app", "api", start + 30, |v| v + 1.0),
+            Some(6.0)
+        );
+        let (values, samples) = gauges.get_dirty(start + 30);
+        assert_eq!(values, vec![("app".to_owned(), "api".to_owned(), 6.0)]);
+        let bucket = samples[0].3;
+        assert_eq!(samples[0].2, minute(start));
+        assert_eq!((bucket.min, bucket.max, bucket.last), (5.0, 6.0, 6.0));
+
+        // 没有修改的分钟以当前值作为采样, 当前值不需要重新写入
+        //
+        // Minutes without changes are sampled with the current value, which is not written again
+        let (values, samples) = gauges.get_dirty(start + 3 * 60);
+        assert!(values.is_empty());
+        let times: Vec<i64> = samples.iter().map(|sample| sample.2).collect();
+        assert_eq!(
+            times,
+            vec![minute(start) + 60, minute(start) + 120, minute(start) + 180]
+        );
+        assert!(samples.iter().all(|sample| sample.3.last == 6.0));
+        assert!(gauges.get_dirty(start + 3 * 60 + 30).1.is_empty());
+    }
+
+    #[test]
+    fn overflow() {
+        let gauges = AllGauge::default();
+        assert_eq!(gauges.update("app", "api", 0, |_| f64::MAX), Some(f64::MAX));
+        assert_eq!(gauges.update("app", "api", 0, |v| v + f64::MAX), None);
+        assert_eq!(gauges.update("app", "api", 0, |v| -v - f64::MAX), None);
+        assert_eq!(gauges.get("app", "api"), Some(f64::MAX));
+        assert_eq!(gauges.get_pending("app", "api", 0, 0)[0].1.count, 1);
+    }
+}
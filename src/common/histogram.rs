@@ -0,0 +1,212 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::model::vo::histogram::HistogramVO;
+
+/// 每个 2 的幂区间内线性划分的子桶位数, 相对误差约为 2^-8 = 0.4%
+///
+/// Bits of the linear sub-buckets within every power of 2, the relative error is about 2^-8 = 0.4%
+const SUB_BITS: u32 = 7;
+
+const SHIFT: u32 = f64::MANTISSA_DIGITS - 1 - SUB_BITS;
+
+const HOUR: i64 = 60 * 60;
+
+/// 时间戳所在的小时, 为该小时开始的时间戳
+///
+/// The hour of the timestamp, as the timestamp of its start
+pub fn hour(time: i64) -> i64 {
+    time - time.rem_euclid(HOUR)
+}
+
+/// 对数线性直方图
+///
+/// 非负浮点数的位表示随数值单调递增, 取指数与尾数的高位作为桶的索引,
+/// 即每个 2 的幂区间被线性划分为 2^SUB_BITS 个桶.
+///
+/// Log-linear histogram
+///
+/// The bits of non-negative floats increase with the value, the exponent and the high bits
+/// of the mantissa are the bucket index, so every power of 2 is split into 2^SUB_BITS linear buckets.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Histogram {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    buckets: BTreeMap<u64, u64>,
+}
+
+impl Histogram {
+    pub fn add(&mut self, value: f64) {
+        if self.count == 0 || value < self.min {
+            self.min = value;
+        }
+        if self.count == 0 || value > self.max {
+            self.max = value;
+        }
+        self.count += 1;
+        self.sum += value;
+        *self.buckets.entry(value.to_bits() >> SHIFT).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 || other.min < self.min {
+            self.min = other.min;
+        }
+        if self.count == 0 || other.max > self.max {
+            self.max = other.max;
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+        for (index, count) in other.buckets.iter() {
+            *self.buckets.entry(*index).or_default() += count;
+        }
+    }
+
+    /// 第 `q` 分位数, 取所在桶的中点
+    ///
+    /// The `q` quantile, the midpoint of its bucket
+    pub fn quantile(&self, q: f64) -> f64 {
+        let rank = ((q * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter() {
+            seen += count;
+            if seen >= rank {
+                let low = f64::from_bits(index << SHIFT);
+                let high = f64::from_bits((index + 1) << SHIFT);
+                return ((low + high) / 2.0).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+
+    pub fn to_vo(&self, from: i64, to: i64) -> HistogramVO {
+        let empty = self.count == 0;
+        HistogramVO {
+            from,
+            to,
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: if empty {
+                0.0
+            } else {
+                self.sum / self.count as f64
+            },
+            p50: if empty { 0.0 } else { self.quantile(0.5) },
+            p90: if empty { 0.0 } else { self.quantile(0.9) },
+            p99: if empty { 0.0 } else { self.quantile(0.99) },
+        }
+    }
+}
+
+/// 一个 api 每小时的直方图, 以及是否有未写入数据库的改动
+///
+/// Hourly histograms of an api, and whether they have changes not yet written to the database
+type Hourly = Arc<Mutex<BTreeMap<i64, (Histogram, bool)>>>;
+
+type HistogramApi = Arc<RwLock<HashMap<String, Hourly>>>;
+
+/// 记录所有 api 的直方图
+///
+/// Record the histograms of all apis
+#[derive(Default)]
+pub struct AllHistogram {
+    map: Arc<RwLock<HashMap<String, HistogramApi>>>,
+}
+
+impl AllHistogram {
+    fn hourly(&self, app: &str, api: &str) -> Hourly {
+        if let Some(hourly) = self
+            .map
+            .read()
+            .get(app)
+            .and_then(|apis| apis.read().get(api).cloned())
+        {
+            return hourly;
+        }
+        let apis = self.map.write().entry(app.to_owned()).or_default().clone();
+        let hourly = apis.write().entry(api.to_owned()).or_default().clone();
+        hourly
+    }
+
+    /// 设置某小时的直方图, 用于从数据库恢复
+    ///
+    /// Set the histogram of an hour, used to restore from the database
+    pub fn set(&self, app: &str, api: &str, time: i64, histogram: Histogram) {
+        self.hourly(app, api)
+            .lock()
+            .insert(time, (histogram, false));
+    }
+
+    pub fn add(&self, app: &str, api: &str, value: f64, now: i64) {
+        let hourly = self.hourly(app, api);
+        let mut hourly = hourly.lock();
+        let (histogram, dirty) = hourly.entry(hour(now)).or_default();
+        histogram.add(value);
+        *dirty = true;
+    }
+
+    /// 获取内存中 `from` 至 `to` 小时内的直方图, 内存中的直方图包含了数据库中同一小时的部分
+    ///
+    /// Get the histograms in memory from `from` to `to` hours, a histogram in memory contains
+    /// what is in the database for the same hour
+    pub fn get_range(&self, app: &str, api: &str, from: i64, to: i64) -> Vec<(i64, Histogram)> {
+        let hourly = match self.map.read().get(app) {
+            Some(apis) => apis.read().get(api).cloned(),
+            None => None,
+        };
+        match hourly {
+            Some(hourly) => hourly
+                .lock()
+                .range(from..=to)
+                .map(|(time, (histogram, _))| (*time, histogram.clone()))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// 获取所有有改动的直方图并清除改动标记, 同时丢弃当前小时以前已写入的直方图
+    ///
+    /// Get all changed histograms and clear their flags, histograms before the current hour
+    /// that were written are dropped
+    pub fn get_dirty(&self, now: i64) -> Vec<(String, String, i64, Histogram)> {
+        let current = hour(now);
+        let mut dirty = vec![];
+        let apps: Vec<(String, HistogramApi)> = self
+            .map
+            .read()
+            .iter()
+            .map(|(app, apis)| (app.to_owned(), apis.clone()))
+            .collect();
+        for (app, apis) in apps {
+            let apis: Vec<(String, Hourly)> = apis
+                .read()
+                .iter()
+                .map(|(api, hourly)| (api.to_owned(), hourly.clone()))
+                .collect();
+            for (api, hourly) in apis {
+                let mut hourly = hourly.lock();
+                // 本次才写入的直方图留到下次同步再丢弃, 以免写入前查询不到
+                //
+                // Histograms written by this sync are dropped by the next one,
+                // so they can still be queried before they are written
+                hourly.retain(|time, (_, changed)| *time >= current || *changed);
+                for (time, (histogram, changed)) in hourly.iter_mut() {
+                    if *changed {
+                        dirty.push((app.to_owned(), api.to_owned(), *time, histogram.clone()));
+                        *changed = false;
+                    }
+                }
+            }
+        }
+        dirty
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use crate::model::vo::stream::{ApiDelta, StreamVO};
+
+/// 推送给订阅者的事件
+///
+/// Event pushed to subscribers
+pub struct StreamEvent {
+    pub vo: StreamVO,
+    /// 不带速率的数据
+    ///
+    /// Data without rates
+    pub plain: String,
+    /// 带速率的数据
+    ///
+    /// Data with rates
+    pub rates: String,
+}
+
+impl StreamEvent {
+    pub fn new(vo: StreamVO) -> Self {
+        let rates = serde_json::to_string(&vo).unwrap();
+        let plain = serde_json::to_string(&StreamVO {
+            total: vo.total,
+            apis: vo
+                .apis
+                .iter()
+                .map(|delta| ApiDelta {
+                    rate: None,
+                    ..delta.clone()
+                })
+                .collect(),
+        })
+        .unwrap();
+        Self { vo, plain, rates }
+    }
+}
+
+/// app 的推送通道
+///
+/// Push channel of an app
+pub struct Channel {
+    pub sender: Sender<Arc<StreamEvent>>,
+    /// 上次推送时各 api 的调用次数
+    ///
+    /// Counts of each api at the last push
+    pub prev: HashMap<String, i64>,
+    /// 上次推送时是否有变化
+    ///
+    /// Whether anything changed at the last push
+    pub changed: bool,
+}
+
+/// 每个 app 的推送通道
+///
+/// Push channel of each app
+#[derive(Default)]
+pub struct Hub {
+    map: Arc<RwLock<HashMap<String, Arc<Mutex<Channel>>>>>,
+}
+
+impl Hub {
+    /// 订阅 app 的推送, 新建通道时以 `counts` 作为初始值
+    ///
+    /// Subscribe to the pushes of the app, `counts` is the initial value when a new channel is made
+    pub fn subscribe(
+        &self,
+        app: &str,
+        counts: impl FnOnce() -> HashMap<String, i64>,
+    ) -> Receiver<Arc<StreamEvent>> {
+        if let Some(channel) = self.map.read().get(app) {
+            return channel.lock().sender.subscribe();
+        }
+        let channel = self
+            .map
+            .write()
+            .entry(app.to_owned())
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(Channel {
+                    sender: broadcast::channel(16).0,
+                    prev: counts(),
+                    changed: false,
+                }))
+            })
+            .clone();
+        let receiver = channel.lock().sender.subscribe();
+        receiver
+    }
+
+    /// 获取有订阅者的 app, 并移除没有订阅者的通道
+    ///
+    /// Get the apps with subscribers and remove the channels without any
+    pub fn get_apps(&self) -> Vec<(String, Arc<Mutex<Channel>>)> {
+        let mut map = self.map.write();
+        map.retain(|_, channel| channel.lock().sender.receiver_count() > 0);
+        map.iter()
+            .map(|(app, channel)| (app.to_owned(), channel.clone()))
+            .collect()
+    }
+}
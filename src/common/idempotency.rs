@@ -0,0 +1,308 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+
+use crate::model::Idempotency;
+
+/// 幂等键的最大长度
+///
+/// Maximum length of an idempotency key
+const MAX_KEY_LEN: usize = 255;
+
+/// 检查幂等键是否合法, 只允许可见的 ASCII 字符
+///
+/// Check whether the idempotency key is valid, only visible ASCII characters are allowed
+pub fn is_valid(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_KEY_LEN && key.chars().all(|c| c.is_ascii_graphic())
+}
+
+/// 开始处理请求的结果
+///
+/// Result of beginning a request
+pub enum Begin {
+    /// 新的幂等键, 已标记为处理中
+    ///
+    /// A new idempotency key, marked as in progress
+    New,
+    /// 已处理过, 附带原来的调用次数
+    ///
+    /// Already handled, with the original count
+    Done(i64),
+    /// 相同的请求正在处理中
+    ///
+    /// The same request is in progress
+    InProgress,
+    /// 幂等键已被其他 api 使用
+    ///
+    /// The key is used by another api
+    Mismatch,
+}
+
+/// 幂等键记录的内容
+///
+/// What is recorded for an idempotency key
+struct Entry {
+    /// 使用幂等键的 api
+    ///
+    /// The api the key is used with
+    api: String,
+    /// 调用次数, 处理中时为空
+    ///
+    /// The count, empty while in progress
+    count: Option<i64>,
+    /// 过期时间
+    ///
+    /// Expiry time
+    expires: i64,
+}
+
+/// 一个 app 最近的幂等键
+///
+/// Recent idempotency keys of an app
+#[derive(Default)]
+struct Cache {
+    /// 幂等键对应的记录
+    ///
+    /// The entry of each key
+    map: HashMap<String, Entry>,
+    /// 所有幂等键, 包括处理中的, 按过期时间排序
+    ///
+    /// All keys, including the ones in progress, sorted by expiry
+    order: VecDeque<(i64, String)>,
+}
+
+impl Cache {
+    /// 移除过期的幂等键, 并在数量达到上限时移除最早的, 为新的幂等键留出位置
+    ///
+    /// Remove the expired keys, and the oldest ones when the capacity is reached,
+    /// making room for a new key
+    fn evict(&mut self, now: i64, capacity: usize) {
+        while let Some((expires, _)) = self.order.front() {
+            if *expires > now && self.map.len() < capacity {
+                break;
+            }
+            let (expires, key) = self.order.pop_front().unwrap();
+            if self
+                .map
+                .get(&key)
+                .is_some_and(|entry| entry.expires == expires)
+            {
+                self.map.remove(&key);
+            }
+        }
+    }
+}
+
+/// 记录所有 app 最近的幂等键, 重复的请求返回原来的结果
+///
+/// Record the recent idempotency keys of all apps, a duplicate request returns the original result
+pub struct AllIdempotency {
+    map: Arc<RwLock<HashMap<String, Arc<Mutex<Cache>>>>>,
+    /// 幂等键保留的秒数
+    ///
+    /// Seconds an idempotency key is kept
+    ttl: i64,
+    /// 每个 app 最多保留的幂等键数量
+    ///
+    /// Maximum number of keys kept per app
+    capacity: usize,
+    /// 是否写入数据库
+    ///
+    /// Whether to write to the database
+    persist: bool,
+    /// 等待写入数据库的幂等键
+    ///
+    /// Keys waiting to be written to the database
+    wait: Mutex<Vec<Idempotency>>,
+}
+
+impl AllIdempotency {
+    pub fn new(ttl: i64, capacity: usize, persist: bool) -> Self {
+        Self {
+            map: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+            capacity,
+            persist,
+            wait: Mutex::new(vec![]),
+        }
+    }
+
+    fn cache(&self, app: &str) -> Arc<Mutex<Cache>> {
+        if let Some(cache) = self.map.read().get(app) {
+            return cache.clone();
+        }
+        self.map.write().entry(app.to_owned()).or_default().clone()
+    }
+
+    /// 设置已完成的幂等键, 用于从数据库恢复, 需要按过期时间顺序调用
+    ///
+    /// Set a finished key, used to restore from the database, must be called in order of expiry
+    pub fn restore(&self, app: &str, api: String, key: String, count: i64, expires: i64, now: i64) {
+        let cache = self.cache(app);
+        let mut cache = cache.lock();
+        cache.evict(now, self.capacity);
+        let entry = Entry {
+            api,
+            count: Some(count),
+            expires,
+        };
+        cache.map.insert(key.clone(), entry);
+        cache.order.push_back((expires, key));
+    }
+
+    /// 开始处理带幂等键的请求, 幂等键在 app 内唯一, 不能用于其他 api
+    ///
+    /// Begin a request with an idempotency key, the key is unique within the app and cannot
+    /// be used with another api
+    pub fn begin(&self, app: &str, api: &str, key: &str, now: i64) -> Begin {
+        let cache = self.cache(app);
+        let mut cache = cache.lock();
+        match cache.map.get(key) {
+            Some(entry) if entry.expires > now => {
+                if entry.api != api {
+                    return Begin::Mismatch;
+                }
+                match entry.count {
+                    Some(count) => Begin::Done(count),
+                    None => Begin::InProgress,
+                }
+            }
+            _ => {
+                cache.evict(now, self.capacity);
+                // 处理中的幂等键同样计入容量, 未完成的 (如请求中途失败) 在过期后移除
+                //
+                // Keys in progress count towards the capacity too, unfinished ones
+                // (such as a request that failed midway) are removed after they expire
+                let expires = now + self.ttl;
+                let entry = Entry {
+                    api: api.to_owned(),
+                    count: None,
+                    expires,
+                };
+                cache.map.insert(key.to_owned(), entry);
+                cache.order.push_back((expires, key.to_owned()));
+                Begin::New
+            }
+        }
+    }
+
+    /// 完成请求, 记录调用次数
+    ///
+    /// Finish the request and record the count
+    pub fn finish(&self, app: &str, api: &str, key: &str, count: i64, now: i64) {
+        let cache = self.cache(app);
+        let mut cache = cache.lock();
+        let expires = now + self.ttl;
+        let entry = Entry {
+            api: api.to_owned(),
+            count: Some(count),
+            expires,
+        };
+        cache.map.insert(key.to_owned(), entry);
+        cache.order.push_back((expires, key.to_owned()));
+        if self.persist {
+            self.wait.lock().push(Idempotency {
+                app: app.to_owned(),
+                api: api.to_owned(),
+                key: key.to_owned(),
+                count,
+                expires,
+            });
+        }
+    }
+
+    /// 请求失败, 移除幂等键以便重试
+    ///
+    /// The request failed, remove the key so it can be retried
+    pub fn abort(&self, app: &str, key: &str) {
+        self.cache(app).lock().map.remove(key);
+    }
+
+    /// 获取等待写入的幂等键数量
+    ///
+    /// Get the number of keys waiting to be written
+    pub fn pending(&self) -> usize {
+        self.wait.lock().len()
+    }
+
+    /// 获取所有需要写入的幂等键并清空
+    ///
+    /// Get all keys that need to be written and clear them
+    pub fn get_wait(&self) -> Vec<Idempotency> {
+        std::mem::take(&mut *self.wait.lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay() {
+        let all = AllIdempotency::new(10, 100, true);
+        assert!(matches!(all.begin("app1", "api1", "k", 0), Begin::New));
+        assert!(matches!(
+            all.begin("app1", "api1", "k", 0),
+            Begin::InProgress
+        ));
+        all.finish("app1", "api1", "k", 5, 0);
+        assert!(matches!(all.begin("app1", "api1", "k", 1), Begin::Done(5)));
+
+        // 其他 api 不能使用相同的幂等键, 其他 app 不受影响
+        //
+        // Another api cannot use the same key, other apps are not affected
+        assert!(matches!(all.begin("app1", "api2", "k", 1), Begin::Mismatch));
+        assert!(matches!(all.begin("app2", "api2", "k", 1), Begin::New));
+
+        // 过期后可以用于任意 api
+        //
+        // After expiry the key can be used with any api
+        assert!(matches!(all.begin("app1", "api2", "k", 10), Begin::New));
+        let wait = all.get_wait();
+        assert_eq!(wait.len(), 1);
+        assert_eq!(
+            (
+                wait[0].app.as_str(),
+                wait[0].api.as_str(),
+                wait[0].key.as_str()
+            ),
+            ("app1", "api1", "k")
+        );
+        assert_eq!((wait[0].count, wait[0].expires), (5, 10));
+    }
+
+    #[test]
+    fn abort_and_restore() {
+        let all = AllIdempotency::new(10, 100, false);
+        assert!(matches!(all.begin("app1", "api1", "k", 0), Begin::New));
+        all.abort("app1", "k");
+        assert!(matches!(all.begin("app1", "api2", "k", 0), Begin::New));
+        assert_eq!(all.pending(), 0);
+
+        all.restore("app2", "api1".to_owned(), "k".to_owned(), 3, 10, 0);
+        assert!(matches!(all.begin("app2", "api1", "k", 5), Begin::Done(3)));
+        assert!(matches!(all.begin("app2", "api2", "k", 5), Begin::Mismatch));
+    }
+
+    #[test]
+    fn in_progress_counts_towards_capacity() {
+        let all = AllIdempotency::new(10, 2, false);
+        for key in ["a", "b", "c"] {
+            assert!(matches!(all.begin("app1", "api1", key, 0), Begin::New));
+        }
+        let cache = all.cache("app1");
+        assert_eq!(cache.lock().map.len(), 2);
+        assert!(!cache.lock().map.contains_key("a"));
+
+        // 未完成的幂等键过期后可以重新使用
+        //
+        // An unfinished key can be used again after it expires
+        assert!(matches!(
+            all.begin("app1", "api1", "c", 5),
+            Begin::InProgress
+        ));
+        assert!(matches!(all.begin("app1", "api2", "c", 10), Begin::New));
+        assert!(cache.lock().map.len() <= 2);
+    }
+}
@@ -0,0 +1,376 @@
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc,
+};
+
+use hashbrown::{HashMap, HashSet};
+use parking_lot::{Mutex, RwLock};
+use serde_json::Value;
+
+use crate::{model::vo::kv::KvVO, util};
+
+/// 字符串值的最大长度
+///
+/// Maximum length of a string value
+const MAX_STRING_LEN: usize = 4096;
+
+/// JSON 值序列化后的最大长度
+///
+/// Maximum serialized length of a JSON value
+const MAX_JSON_LEN: usize = 64 * 1024;
+
+/// 过期时间的最大秒数 (100 年)
+///
+/// Maximum time to live in seconds (100 years)
+pub const MAX_TTL: i64 = 100 * 365 * 24 * 3600;
+
+/// 值的类型, 每种类型有独立的 key 空间
+///
+/// Value type, every type has its own key space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Bool,
+    String,
+    Json,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 3] = [Kind::Bool, Kind::String, Kind::Json];
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "bool" => Some(Kind::Bool),
+            "string" => Some(Kind::String),
+            "json" => Some(Kind::Json),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Bool => "bool",
+            Kind::String => "string",
+            Kind::Json => "json",
+        }
+    }
+
+    /// 检查值是否符合类型
+    ///
+    /// Check whether the value matches the type
+    pub fn check(&self, value: &Value) -> bool {
+        match self {
+            Kind::Bool => value.is_boolean(),
+            Kind::String => value.as_str().is_some_and(|s| s.len() <= MAX_STRING_LEN),
+            Kind::Json => value.to_string().len() <= MAX_JSON_LEN,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub value: Value,
+    /// 每次写入时取自递增的序号, 用于比较并设置
+    ///
+    /// Taken from an increasing sequence on every write, used for compare-and-set
+    pub version: i64,
+    /// 过期时间戳
+    ///
+    /// Expiry timestamp
+    pub expires: Option<i64>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn to_vo(&self) -> KvVO {
+        KvVO {
+            data: self.value.clone(),
+            version: self.version,
+            expires: self.expires,
+        }
+    }
+}
+
+/// 一种类型的键值存储
+///
+/// Key-value store of a type
+pub struct Store {
+    map: Arc<RwLock<HashMap<String, Entry>>>,
+    /// 等待写入数据库的 key
+    ///
+    /// Keys waiting to be written to the database
+    wait: Mutex<HashSet<String>>,
+    /// 版本号的序号, 删除或过期后重新写入的 key 也不会得到用过的版本号.
+    /// 从启动时的微秒时间戳开始, 并且不小于恢复的版本号, 重启后也不会重复
+    ///
+    /// Sequence of versions, so a key written again after a delete or expiry never gets a used version.
+    /// Starts from the microsecond timestamp at startup and is not below any restored version,
+    /// so it does not repeat after a restart
+    seq: AtomicI64,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self {
+            map: Arc::new(RwLock::new(HashMap::new())),
+            wait: Mutex::new(HashSet::new()),
+            seq: AtomicI64::new((util::now_f64() * 1_000_000.0) as i64),
+        }
+    }
+}
+
+impl Store {
+    /// 设置值, 用于从数据库恢复
+    ///
+    /// Set a value, used to restore from the database
+    pub fn restore(&self, key: String, entry: Entry) {
+        self.seq.fetch_max(entry.version, Ordering::SeqCst);
+        self.map.write().insert(key, entry);
+    }
+
+    pub fn get(&self, key: &str, now: i64) -> Option<KvVO> {
+        self.map
+            .read()
+            .get(key)
+            .filter(|entry| !entry.is_expired(now))
+            .map(Entry::to_vo)
+    }
+
+    /// 设置值. 指定 `version` 时只有当前版本相同才会写入, 0 表示 key 不存在,
+    /// 版本不符时返回当前的值. 过期时间超出范围时不过期
+    ///
+    /// Set a value. With a `version` it is only written when the current version matches,
+    /// 0 means the key does not exist. The current value is returned when the version does not match.
+    /// It never expires when the expiry is out of range
+    pub fn set(
+        &self,
+        key: &str,
+        value: Value,
+        ttl: Option<i64>,
+        version: Option<i64>,
+        now: i64,
+    ) -> Result<KvVO, Option<KvVO>> {
+        let mut map = self.map.write();
+        let current = map.get(key).filter(|entry| !entry.is_expired(now));
+        let current_version = current.map(|entry| entry.version).unwrap_or_default();
+        if version.is_some_and(|version| version != current_version) {
+            return Err(current.map(Entry::to_vo));
+        }
+        let entry = Entry {
+            value,
+            version: self.seq.fetch_add(1, Ordering::SeqCst) + 1,
+            expires: ttl.and_then(|ttl| now.checked_add(ttl)),
+        };
+        let vo = entry.to_vo();
+        map.insert(key.to_owned(), entry);
+        self.wait.lock().insert(key.to_owned());
+        Ok(vo)
+    }
+
+    pub fn delete(&self, key: &str, now: i64) -> bool {
+        let removed = self.map.write().remove(key);
+        if removed.is_some() {
+            self.wait.lock().insert(key.to_owned());
+        }
+        removed.is_some_and(|entry| !entry.is_expired(now))
+    }
+
+    /// 移除过期的值, 返回被移除的 key
+    ///
+    /// Remove expired values, returns the removed keys
+    pub fn expire(&self, now: i64) -> Vec<String> {
+        let mut expired = vec![];
+        let mut map = self.map.write();
+        let mut wait = self.wait.lock();
+        map.retain(|key, entry| {
+            if entry.is_expired(now) {
+                wait.insert(key.to_owned());
+                expired.push(key.to_owned());
+                return false;
+            }
+            true
+        });
+        expired
+    }
+
+    /// 获取等待写入的值的数量
+    ///
+    /// Get the number of values waiting to be written
+    pub fn pending(&self) -> usize {
+        self.wait.lock().len()
+    }
+
+    /// 获取所有需要写入的值并清空, 值为空表示已被删除
+    ///
+    /// Get all values that need to be written and clear them, an empty value means it was deleted
+    pub fn get_wait(&self) -> Vec<(String, Option<Entry>)> {
+        let wait = std::mem::take(&mut *self.wait.lock());
+        let map = self.map.read();
+        wait.into_iter()
+            .map(|key| {
+                let entry = map.get(&key).cloned();
+                (key, entry)
+            })
+            .collect()
+    }
+}
+
+/// 所有类型的键值存储
+///
+/// Key-value stores of all types
+#[derive(Default)]
+pub struct AllKv {
+    bools: Store,
+    strings: Store,
+    jsons: Store,
+}
+
+impl AllKv {
+    pub fn store(&self, kind: Kind) -> &Store {
+        match kind {
+            Kind::Bool => &self.bools,
+            Kind::String => &self.strings,
+            Kind::Json => &self.jsons,
+        }
+    }
+
+    /// 移除所有过期的值, 返回被移除的类型与 key
+    ///
+    /// Remove all expired values, returns the types and keys removed
+    pub fn expire(&self, now: i64) -> Vec<(Kind, String)> {
+        Kind::ALL
+            .iter()
+            .flat_map(|kind| {
+                self.store(*kind)
+                    .expire(now)
+                    .into_iter()
+                    .map(|key| (*kind, key))
+            })
+            .collect()
+    }
+
+    /// 获取所有类型等待写入的值的数量
+    ///
+    /// Get the number of values of all types waiting to be written
+    pub fn pending(&self) -> usize {
+        Kind::ALL
+            .iter()
+            .map(|kind| self.store(*kind).pending())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn compare_and_set() {
+        let store = Store::default();
+        // 版本 0 表示只在 key 不存在时写入
+        //
+        // Version 0 only writes when the key does not exist
+        let first = store
+            .set("key", json!("a"), None, Some(0), 100)
+            .unwrap()
+            .version;
+        let current = store
+            .set("key", json!("b"), None, Some(0), 100)
+            .unwrap_err()
+            .unwrap();
+        assert_eq!((current.data, current.version), (json!("a"), first));
+
+        let second = store
+            .set("key", json!("b"), None, Some(first), 100)
+            .unwrap()
+            .version;
+        assert!(second > first);
+        assert!(store
+            .set("key", json!("c"), None, Some(first), 100)
+            .is_err());
+        let third = store
+            .set("key", json!("c"), None, None, 100)
+            .unwrap()
+            .version;
+        assert!(third > second);
+        assert_eq!(store.get("key", 100).unwrap().data, json!("c"));
+
+        // 不存在的 key 只接受版本 0
+        //
+        // A missing key only accepts version 0
+        assert!(matches!(
+            store.set("other", json!(1), None, Some(1), 100),
+            Err(None)
+        ));
+
+        // 删除后重新写入的 key 不会重复使用版本号, 旧的版本号无法覆盖新的值
+        //
+        // A key written again after a delete never reuses a version, so an old one cannot overwrite the new value
+        assert!(store.delete("key", 100));
+        let fourth = store
+            .set("key", json!("d"), None, Some(0), 100)
+            .unwrap()
+            .version;
+        assert!(fourth > third);
+        assert!(store
+            .set("key", json!("e"), None, Some(third), 100)
+            .is_err());
+    }
+
+    #[test]
+    fn restore_keeps_versions_increasing() {
+        let store = Store::default();
+        let restored = i64::MAX / 2;
+        let entry = Entry {
+            value: json!(true),
+            version: restored,
+            expires: None,
+        };
+        store.restore("key".to_owned(), entry);
+        let vo = store.set("other", json!(false), None, None, 100).unwrap();
+        assert!(vo.version > restored);
+    }
+
+    #[test]
+    fn ttl() {
+        let store = Store::default();
+        let vo = store.set("key", json!(true), Some(10), None, 100).unwrap();
+        assert_eq!(vo.expires, Some(110));
+        assert!(store.get("key", 109).is_some());
+        assert!(store.get("key", 110).is_none());
+
+        // 过期的 key 视为不存在, 只接受版本 0, 并得到新的版本号
+        //
+        // An expired key counts as missing, only accepts version 0 and gets a new version
+        assert!(!store.delete("key", 110));
+        let old = store
+            .set("key", json!(true), Some(10), None, 100)
+            .unwrap()
+            .version;
+        assert!(store
+            .set("key", json!(false), None, Some(old), 110)
+            .is_err());
+        let vo = store.set("key", json!(false), None, Some(0), 110).unwrap();
+        assert!(vo.version > old);
+        assert_eq!(vo.expires, None);
+
+        // 超出范围的过期时间不会回绕到过去
+        //
+        // An out of range expiry does not wrap around into the past
+        let vo = store
+            .set("huge", json!(true), Some(i64::MAX), None, 100)
+            .unwrap();
+        assert_eq!(vo.expires, None);
+        assert!(store.get("huge", 100).is_some());
+
+        store.set("gone", json!(true), Some(1), None, 100).unwrap();
+        store.get_wait();
+        assert_eq!(store.expire(101), vec!["gone".to_owned()]);
+        assert_eq!(store.get_wait()[0].0, "gone");
+        assert!(store.get_wait().is_empty());
+    }
+}
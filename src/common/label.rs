@@ -0,0 +1,253 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc,
+    },
+};
+
+use hashbrown::HashMap;
+use parking_lot::RwLock;
+
+use crate::{model::vo::label::LabelGroupVO, util};
+
+/// 每次调用最多的标签数量
+///
+/// Maximum number of labels per call
+const MAX_LABELS: usize = 8;
+
+/// 标签值的最大长度
+///
+/// Maximum length of a label value
+const MAX_VALUE_LEN: usize = 64;
+
+pub type Labels = BTreeMap<String, String>;
+
+/// 检查标签是否合法
+///
+/// Check whether the labels are valid
+pub fn is_valid(labels: &Labels) -> bool {
+    labels.len() <= MAX_LABELS
+        && labels.iter().all(|(key, value)| {
+            util::is_valid(key) && !value.is_empty() && value.len() <= MAX_VALUE_LEN
+        })
+}
+
+/// 标签组合的唯一表示, 即按键排序的 JSON
+///
+/// Canonical form of a label combination, the JSON sorted by key
+pub fn key(labels: &Labels) -> String {
+    serde_json::to_string(labels).unwrap()
+}
+
+/// 一个标签组合的调用次数
+///
+/// Number of calls of a label combination
+struct LabelCount {
+    labels: Labels,
+    count: AtomicI64,
+    /// 是否有未写入数据库的改动
+    ///
+    /// Whether there are changes not yet written to the database
+    dirty: AtomicBool,
+}
+
+type LabelApi = Arc<RwLock<HashMap<String, Arc<LabelCount>>>>;
+
+type LabelApp = Arc<RwLock<HashMap<String, LabelApi>>>;
+
+/// 记录所有 api 各标签组合的调用次数
+///
+/// Record the number of calls of each label combination of all apis
+#[derive(Default)]
+pub struct AllLabel {
+    map: Arc<RwLock<HashMap<String, LabelApp>>>,
+}
+
+impl AllLabel {
+    fn get_api(&self, app: &str, api: &str) -> Option<LabelApi> {
+        self.map.read().get(app)?.read().get(api).cloned()
+    }
+
+    /// 设置标签组合的调用次数, 用于从数据库恢复
+    ///
+    /// Set the number of calls of a label combination, used to restore from the database
+    pub fn set(&self, app: &str, api: &str, labels: Labels, count: i64) {
+        let app = self.map.write().entry(app.to_owned()).or_default().clone();
+        let api = app.write().entry(api.to_owned()).or_default().clone();
+        api.write().insert(
+            key(&labels),
+            Arc::new(LabelCount {
+                labels,
+                count: AtomicI64::new(count),
+                dirty: AtomicBool::new(false),
+            }),
+        );
+    }
+
+    /// 标签组合已存在, 或 api 的组合数量未达到上限, 只用于在记录调用前提前拒绝
+    ///
+    /// The label combination exists, or the api has not reached the combination limit,
+    /// only used to reject early before recording the call
+    pub fn check(&self, app: &str, api: &str, key: &str, limit: usize) -> bool {
+        match self.get_api(app, api) {
+            Some(api) => {
+                let api = api.read();
+                api.contains_key(key) || api.len() < limit
+            }
+            None => limit > 0,
+        }
+    }
+
+    /// 增加标签组合的调用次数, 新的组合在同一个写锁内检查上限并插入, 超出上限时返回 false
+    ///
+    /// Add to the number of calls of a label combination, a new combination is checked against
+    /// the limit and inserted under the same write lock, returns false beyond the limit
+    pub fn add(&self, app: &str, api: &str, key: String, labels: Labels, limit: usize) -> bool {
+        let api = match self.get_api(app, api) {
+            Some(api) => api,
+            None => {
+                let app = self.map.write().entry(app.to_owned()).or_default().clone();
+                let api = app.write().entry(api.to_owned()).or_default().clone();
+                api
+            }
+        };
+        let count = api.read().get(&key).cloned();
+        let count = match count {
+            Some(count) => count,
+            None => {
+                let mut api = api.write();
+                if !api.contains_key(&key) && api.len() >= limit {
+                    return false;
+                }
+                api.entry(key)
+                    .or_insert_with(|| {
+                        Arc::new(LabelCount {
+                            labels,
+                            count: AtomicI64::new(0),
+                            dirty: AtomicBool::new(false),
+                        })
+                    })
+                    .clone()
+            }
+        };
+        count.count.fetch_add(1, Ordering::Relaxed);
+        count.dirty.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// 筛选包含 `filter` 中所有标签的组合, 并按 `group` 中的键分组
+    ///
+    /// Filter the combinations containing all labels in `filter`, and group them by the keys in `group`
+    pub fn query(
+        &self,
+        app: &str,
+        api: &str,
+        filter: &Labels,
+        group: &[String],
+    ) -> (i64, Vec<LabelGroupVO>) {
+        let api = match self.get_api(app, api) {
+            Some(api) => api,
+            None => return (0, vec![]),
+        };
+        let mut total = 0;
+        let mut groups: HashMap<String, LabelGroupVO> = HashMap::new();
+        for count in api.read().values() {
+            if !filter
+                .iter()
+                .all(|(key, value)| count.labels.get(key) == Some(value))
+            {
+                continue;
+            }
+            let n = count.count.load(Ordering::Relaxed);
+            total += n;
+            if group.is_empty() {
+                continue;
+            }
+            let labels: Labels = group
+                .iter()
+                .filter_map(|key| {
+                    count
+                        .labels
+                        .get(key)
+                        .map(|value| (key.to_owned(), value.to_owned()))
+                })
+                .collect();
+            groups
+                .entry(self::key(&labels))
+                .or_insert_with(|| LabelGroupVO { labels, count: 0 })
+                .count += n;
+        }
+        let mut groups: Vec<LabelGroupVO> = groups.into_values().collect();
+        groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+        (total, groups)
+    }
+
+    /// 获取所有有改动的标签组合并清除改动标记
+    ///
+    /// Get all changed label combinations and clear their flags
+    pub fn get_dirty(&self) -> Vec<(String, String, String, i64)> {
+        let mut dirty = vec![];
+        let apps: Vec<(String, LabelApp)> = self
+            .map
+            .read()
+            .iter()
+            .map(|(app, apis)| (app.to_owned(), apis.clone()))
+            .collect();
+        for (app, apis) in apps {
+            let apis: Vec<(String, LabelApi)> = apis
+                .read()
+                .iter()
+                .map(|(api, counts)| (api.to_owned(), counts.clone()))
+                .collect();
+            for (api, counts) in apis {
+                for (key, count) in counts.read().iter() {
+                    if count.dirty.swap(false, Ordering::Relaxed) {
+                        dirty.push((
+                            app.to_owned(),
+                            api.to_owned(),
+                            key.to_owned(),
+                            count.count.load(Ordering::Relaxed),
+                        ));
+                    }
+                }
+            }
+        }
+        dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_limit() {
+        let labels = Arc::new(AllLabel::default());
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                let labels = labels.clone();
+                std::thread::spawn(move || {
+                    (0..100)
+                        .filter(|i| {
+                            let map = Labels::from([("n".to_owned(), format!("{thread}-{i}"))]);
+                            labels.add("app", "api", key(&map), map, 10)
+                        })
+                        .count()
+                })
+            })
+            .collect();
+        let added: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+        assert_eq!(added, 10);
+        assert_eq!(labels.get_api("app", "api").unwrap().read().len(), 10);
+
+        // 已有的组合不受上限影响
+        //
+        // Existing combinations are not affected by the limit
+        let existing = labels.get_dirty().pop().unwrap().2;
+        let map: Labels = serde_json::from_str(&existing).unwrap();
+        assert!(labels.add("app", "api", existing.to_owned(), map, 10));
+        assert!(labels.check("app", "api", &existing, 10));
+        assert!(!labels.check("app", "api", "{}", 10));
+    }
+}
@@ -0,0 +1,324 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+
+use crate::model::{vo::limit::LimitVO, Limit};
+
+/// 限流算法
+///
+/// Rate limiting algorithm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    /// 令牌桶: 容量为 capacity, 每秒补充 refill 个令牌
+    ///
+    /// Token bucket: holds capacity tokens, refilled by refill tokens per second
+    TokenBucket { capacity: f64, refill: f64 },
+    /// 滑动日志: 任意 window 秒内最多 capacity 次
+    ///
+    /// Sliding log: at most capacity calls in any window seconds
+    SlidingLog { capacity: usize, window: f64 },
+}
+
+impl Algorithm {
+    pub fn from_limit(limit: &Limit) -> Option<Self> {
+        match &limit.algorithm[..] {
+            "token_bucket" if limit.capacity > 0 && limit.refill > 0.0 => {
+                Some(Algorithm::TokenBucket {
+                    capacity: limit.capacity as f64,
+                    refill: limit.refill,
+                })
+            }
+            "sliding_log" if limit.capacity > 0 && limit.window > 0 => {
+                Some(Algorithm::SlidingLog {
+                    capacity: limit.capacity as usize,
+                    window: limit.window as f64,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 每个 key 的限流状态
+///
+/// Rate limiting state of each key
+enum State {
+    TokenBucket { tokens: f64, last: f64 },
+    SlidingLog(VecDeque<f64>),
+}
+
+/// api 的限流器
+///
+/// Rate limiter of an api
+pub struct Limiter {
+    pub limit: Limit,
+    algorithm: Algorithm,
+    keys: RwLock<HashMap<String, Arc<Mutex<State>>>>,
+}
+
+impl Limiter {
+    pub fn new(limit: Limit, algorithm: Algorithm) -> Self {
+        Self {
+            limit,
+            algorithm,
+            keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 检查并消耗一次额度, 记录的 key 达到 `max_keys` 且没有空闲的 key 可以移除时, 新的 key 返回 None
+    ///
+    /// Check and consume one unit, None for a new key if `max_keys` keys are tracked and none is idle
+    pub fn check(&self, key: &str, now: f64, max_keys: usize) -> Option<LimitVO> {
+        let state = { self.keys.read().get(key).cloned() };
+        let state = match state {
+            Some(state) => state,
+            None => {
+                let mut keys = self.keys.write();
+                if !keys.contains_key(key) && keys.len() >= max_keys {
+                    self.evict_locked(&mut keys, now);
+                    if keys.len() >= max_keys {
+                        return None;
+                    }
+                }
+                keys.entry(key.to_owned())
+                    .or_insert_with(|| {
+                        Arc::new(Mutex::new(match self.algorithm {
+                            Algorithm::TokenBucket { capacity, .. } => State::TokenBucket {
+                                tokens: capacity,
+                                last: now,
+                            },
+                            Algorithm::SlidingLog { .. } => State::SlidingLog(VecDeque::new()),
+                        }))
+                    })
+                    .clone()
+            }
+        };
+
+        let mut state = state.lock();
+        Some(match (&mut *state, self.algorithm) {
+            (State::TokenBucket { tokens, last }, Algorithm::TokenBucket { capacity, refill }) => {
+                *tokens = (*tokens + (now - *last).max(0.0) * refill).min(capacity);
+                *last = now;
+                let allowed = *tokens >= 1.0;
+                if allowed {
+                    *tokens -= 1.0;
+                }
+                LimitVO {
+                    allowed,
+                    remaining: tokens.floor() as i64,
+                    retry_after: match allowed {
+                        true => 0.0,
+                        false => (1.0 - *tokens) / refill,
+                    },
+                }
+            }
+            (State::SlidingLog(log), Algorithm::SlidingLog { capacity, window }) => {
+                while log.front().is_some_and(|time| *time <= now - window) {
+                    log.pop_front();
+                }
+                let allowed = log.len() < capacity;
+                if allowed {
+                    log.push_back(now);
+                }
+                LimitVO {
+                    allowed,
+                    remaining: (capacity - log.len()) as i64,
+                    retry_after: match allowed {
+                        true => 0.0,
+                        false => log.front().map_or(0.0, |time| time + window - now),
+                    },
+                }
+            }
+            _ => unreachable!(),
+        })
+    }
+
+    /// 移除已经恢复到初始状态的 key
+    ///
+    /// Remove keys that are back to their initial state
+    fn evict(&self, now: f64) {
+        self.evict_locked(&mut self.keys.write(), now);
+    }
+
+    /// 在持有写锁时移除空闲的 key, 正在被检查的 key 仍被其他地方引用, 不会被移除
+    ///
+    /// Remove idle keys while holding the write lock, keys being checked are still referenced
+    /// elsewhere and are not removed
+    fn evict_locked(&self, keys: &mut HashMap<String, Arc<Mutex<State>>>, now: f64) {
+        keys.retain(|_, state| {
+            if Arc::strong_count(state) > 1 {
+                return true;
+            }
+            match (&*state.lock(), self.algorithm) {
+                (
+                    State::TokenBucket { tokens, last },
+                    Algorithm::TokenBucket { capacity, refill },
+                ) => tokens + (now - last) * refill < capacity,
+                (State::SlidingLog(log), Algorithm::SlidingLog { window, .. }) => {
+                    log.back().is_some_and(|time| *time > now - window)
+                }
+                _ => false,
+            }
+        });
+    }
+}
+
+type LimitApi = HashMap<String, Arc<Limiter>>;
+
+/// 记录所有 api 的限流器
+///
+/// Record the rate limiters of all apis
+#[derive(Default)]
+pub struct AllLimit {
+    map: Arc<RwLock<HashMap<String, LimitApi>>>,
+}
+
+impl AllLimit {
+    /// 添加或替换限流器, 已有的状态会被清空
+    ///
+    /// Add or replace a rate limiter, the existing state is cleared
+    pub fn set(&self, limiter: Limiter) {
+        self.map
+            .write()
+            .entry(limiter.limit.app.to_owned())
+            .or_default()
+            .insert(limiter.limit.api.to_owned(), Arc::new(limiter));
+    }
+
+    pub fn get(&self, app: &str, api: &str) -> Option<Arc<Limiter>> {
+        self.map.read().get(app)?.get(api).cloned()
+    }
+
+    pub fn remove(&self, app: &str, api: &str) -> bool {
+        let mut map = self.map.write();
+        let removed = map
+            .get_mut(app)
+            .is_some_and(|apis| apis.remove(api).is_some());
+        if map.get(app).is_some_and(|apis| apis.is_empty()) {
+            map.remove(app);
+        }
+        removed
+    }
+
+    /// 清理所有限流器中空闲的 key
+    ///
+    /// Clean up idle keys of all rate limiters
+    pub fn evict(&self, now: f64) {
+        let limiters: Vec<Arc<Limiter>> = self
+            .map
+            .read()
+            .values()
+            .flat_map(|apis| apis.values().cloned())
+            .collect();
+        limiters.iter().for_each(|limiter| limiter.evict(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(algorithm: &str, capacity: i64, refill: f64, window: i64) -> Limit {
+        Limit {
+            app: "app".to_owned(),
+            api: "api".to_owned(),
+            algorithm: algorithm.to_owned(),
+            capacity,
+            refill,
+            window,
+        }
+    }
+
+    fn limiter(algorithm: &str, capacity: i64, refill: f64, window: i64) -> Limiter {
+        let limit = limit(algorithm, capacity, refill, window);
+        let algorithm = Algorithm::from_limit(&limit).unwrap();
+        Limiter::new(limit, algorithm)
+    }
+
+    #[test]
+    fn from_limit() {
+        assert!(Algorithm::from_limit(&limit("token_bucket", 0, 1.0, 0)).is_none());
+        assert!(Algorithm::from_limit(&limit("token_bucket", 1, 0.0, 0)).is_none());
+        assert!(Algorithm::from_limit(&limit("sliding_log", 1, 0.0, 0)).is_none());
+        assert!(Algorithm::from_limit(&limit("fixed", 1, 1.0, 1)).is_none());
+    }
+
+    #[test]
+    fn token_bucket() {
+        let limiter = limiter("token_bucket", 3, 2.0, 0);
+        let check = |now| limiter.check("key", now, 10).unwrap();
+
+        // 开始时可以用完整个容量
+        //
+        // The whole capacity can be used at first
+        for remaining in [2, 1, 0] {
+            let vo = check(100.0);
+            assert!(vo.allowed);
+            assert_eq!(vo.remaining, remaining);
+        }
+        let vo = check(100.0);
+        assert!(!vo.allowed);
+        assert_eq!(vo.retry_after, 0.5);
+
+        // 每秒补充 2 个, 不超过容量
+        //
+        // 2 are refilled per second, up to the capacity
+        assert!(check(100.5).allowed);
+        assert!(!check(100.5).allowed);
+        assert_eq!(check(200.0).remaining, 2);
+        // 其他 key 不受影响
+        //
+        // Other keys are not affected
+        assert_eq!(limiter.check("other", 200.0, 10).unwrap().remaining, 2);
+    }
+
+    #[test]
+    fn sliding_log() {
+        let limiter = limiter("sliding_log", 2, 0.0, 10);
+        let check = |now| limiter.check("key", now, 10).unwrap();
+
+        assert!(check(100.0).allowed);
+        assert!(check(105.0).allowed);
+        let vo = check(109.0);
+        assert!(!vo.allowed);
+        assert_eq!((vo.remaining, vo.retry_after), (0, 1.0));
+        // 拒绝的调用不会记录, 最早的调用在窗口结束时移出
+        //
+        // Rejected calls are not logged, the oldest one leaves at the end of its window
+        let vo = check(110.0);
+        assert!(vo.allowed);
+        assert_eq!(vo.remaining, 0);
+        assert_eq!(check(114.0).retry_after, 1.0);
+    }
+
+    #[test]
+    fn evict() {
+        let limiter = limiter("token_bucket", 2, 1.0, 0);
+        limiter.check("a", 100.0, 10);
+        limiter.check("b", 101.0, 10);
+        // 正在被检查的 key 不会被移除
+        //
+        // A key being checked is not removed
+        let held = limiter.keys.read().get("a").cloned().unwrap();
+        limiter.evict(110.0);
+        assert_eq!(limiter.keys.read().len(), 1);
+        drop(held);
+        limiter.evict(101.5);
+        assert_eq!(limiter.keys.read().len(), 0);
+    }
+
+    #[test]
+    fn max_keys() {
+        let limiter = limiter("sliding_log", 1, 0.0, 10);
+        assert!(limiter.check("a", 100.0, 2).is_some());
+        assert!(limiter.check("b", 100.0, 2).is_some());
+        assert!(limiter.check("c", 105.0, 2).is_none());
+        // 已有的 key 不受影响, 空闲的 key 被移除后可以记录新的 key
+        //
+        // Existing keys are not affected, new keys are tracked once idle ones are removed
+        assert!(!limiter.check("a", 105.0, 2).unwrap().allowed);
+        assert!(limiter.check("c", 110.0, 2).unwrap().allowed);
+        assert_eq!(limiter.keys.read().len(), 1);
+    }
+}
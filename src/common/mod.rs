@@ -1,6 +1,20 @@
+pub mod alert;
 pub mod api;
 pub mod app;
+pub mod count;
+pub mod flag;
+pub mod gauge;
+pub mod histogram;
+pub mod hub;
+pub mod idempotency;
+pub mod kv;
+pub mod label;
+pub mod limit;
+pub mod quota;
 pub mod record;
+pub mod visitor;
+pub mod watch;
+pub mod window;
 
 use std::sync::{atomic::AtomicI64, Arc};
 
@@ -12,13 +26,32 @@ use tokio::sync::OnceCell;
 use crate::{
     common::app::AllApp,
     config::CONFIG,
+    db::{
+        get_alerts, get_counts, get_flags, get_gauges, get_histograms_hour, get_idempotency,
+        get_kv, get_labels, get_limits, get_quotas, get_salt, get_visitors_day, sum_rec, TABLES,
+    },
     model::{Api, App},
+    util,
 };
 
 use self::{
+    alert::AllAlert,
     api::{AllApi, WaitApi},
     app::WaitApp,
+    count::AllCount,
+    flag::{AllFlag, Rule},
+    gauge::AllGauge,
+    histogram::{hour, AllHistogram},
+    hub::Hub,
+    idempotency::AllIdempotency,
+    kv::{AllKv, Entry, Kind},
+    label::AllLabel,
+    limit::{Algorithm, AllLimit, Limiter},
+    quota::{AllQuota, Period, Usage},
     record::WaitRecord,
+    visitor::{day, AllVisitor, Sketch},
+    watch::AllWatch,
+    window::AllWindow,
 };
 
 pub static CONTEXT: OnceCell<ServiceContext> = OnceCell::const_new();
@@ -38,7 +71,7 @@ macro_rules! context {
 }
 
 pub async fn init() -> ServiceContext {
-    let file_path = CONFIG.exe_dir.join("data").join("db.sqlite");
+    let file_path = CONFIG.data_dir.join("db.sqlite");
     let file_path_s = file_path.to_str().unwrap().to_owned();
     let file_path_s = file_path_s.replace("\\\\?\\", "");
     let db_path = format!("sqlite://{}", file_path_s);
@@ -49,7 +82,7 @@ pub async fn init() -> ServiceContext {
         // 如果数据库文件不存在，创建数据库文件
         //
         // Create the data directory if it doesn't exist
-        std::fs::create_dir(file_path.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
         std::fs::File::create(file_path).unwrap();
         // 创建数据库
         //
@@ -77,6 +110,22 @@ pub async fn init() -> ServiceContext {
         .await
         .unwrap();
 
+    // 创建功能表
+    //
+    // Create the feature tables
+    for sql in TABLES {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+
+    // 获取所有告警规则
+    //
+    // Get all alert rules
+    let alerts = get_alerts(&pool)
+        .await
+        .into_iter()
+        .map(|alert| (alert.id, alert))
+        .collect();
+
     // 获取所有 app
     //
     // Get all apps
@@ -107,6 +156,138 @@ pub async fn init() -> ServiceContext {
         apis.insert(app.to_owned(), Arc::new(RwLock::new(apis_part)));
     }
 
+    // 获取所有配额, 并从记录中统计当前周期的用量
+    //
+    // Get all quotas and count the usage of the current period from the records
+    let quotas = AllQuota::default();
+    let now = util::now();
+    for quota in get_quotas(&pool).await {
+        let period = match Period::parse(&quota.period) {
+            Some(period) => period,
+            None => continue,
+        };
+        let names: Vec<String> = match quota.api.is_empty() {
+            true => apis
+                .get(&quota.app)
+                .map(|apis_part| apis_part.read().keys().cloned().collect())
+                .unwrap_or_default(),
+            false => vec![quota.api.to_owned()],
+        };
+        let used = sum_rec(&pool, &quota.app, &names, period.bounds(now).0).await;
+        quotas.set(
+            &quota.app,
+            &quota.api,
+            Usage::new(quota.limit, period, now, used),
+        );
+    }
+
+    // 获取所有限流规则
+    //
+    // Get all rate limit rules
+    let limits = AllLimit::default();
+    for limit in get_limits(&pool).await {
+        if let Some(algorithm) = Algorithm::from_limit(&limit) {
+            limits.set(Limiter::new(limit, algorithm));
+        }
+    }
+
+    // 获取所有标签组合的调用次数
+    //
+    // Get the number of calls of all label combinations
+    let labels = AllLabel::default();
+    for label in get_labels(&pool).await {
+        if let Ok(map) = serde_json::from_str(&label.labels) {
+            labels.set(&label.app, &label.api, map, label.count);
+        }
+    }
+
+    // 获取当天的访客草图
+    //
+    // Get the visitor sketches of the current day
+    let sketches = get_visitors_day(&pool, day(now))
+        .await
+        .into_iter()
+        .filter_map(|visitor| {
+            Sketch::from_bytes(visitor.sketch)
+                .map(|sketch| (visitor.app, visitor.api, visitor.day, sketch))
+        })
+        .collect();
+
+    // 获取当天的盐, 重启后同一访客仍生成相同的标识
+    //
+    // Get the salt of the current day, so the same visitor still derives the same identifier after a restart
+    let salt = get_salt(&pool, day(now)).await;
+
+    // 获取当前小时的直方图
+    //
+    // Get the histograms of the current hour
+    let histograms = AllHistogram::default();
+    for row in get_histograms_hour(&pool, hour(now)).await {
+        if let Ok(histogram) = serde_json::from_str(&row.data) {
+            histograms.set(&row.app, &row.api, row.time, histogram);
+        }
+    }
+
+    // 获取所有仪表的当前值
+    //
+    // Get the current values of all gauges
+    let gauges = AllGauge::default();
+    for gauge in get_gauges(&pool).await {
+        gauges.restore(&gauge.app, &gauge.api, gauge.value, now);
+    }
+
+    // 获取所有以 key 记录的计数
+    //
+    // Get all counters keyed by keys
+    let counts = get_counts(&pool)
+        .await
+        .into_iter()
+        .map(|count| (count.key, Arc::new(AtomicI64::new(count.count))))
+        .collect();
+
+    // 获取键值存储中未过期的值
+    //
+    // Get the values in the key-value store that have not expired
+    let kv = AllKv::default();
+    for row in get_kv(&pool).await {
+        if row.expires.is_some_and(|expires| expires <= now) {
+            continue;
+        }
+        if let (Some(kind), Ok(value)) = (Kind::parse(&row.kind), serde_json::from_str(&row.value))
+        {
+            let entry = Entry {
+                value,
+                version: row.version,
+                expires: row.expires,
+            };
+            kv.store(kind).restore(row.key, entry);
+        }
+    }
+
+    // 获取所有功能开关
+    //
+    // Get all feature flags
+    let flags = AllFlag::default();
+    for flag in get_flags(&pool).await {
+        if let Some(rule) = Rule::from_flag(&flag) {
+            flags.set(&flag.key, rule);
+        }
+    }
+
+    // 获取持久化的幂等键
+    //
+    // Get the persisted idempotency keys
+    let idempotency = AllIdempotency::new(
+        CONFIG.idempotency_ttl,
+        CONFIG.idempotency_capacity,
+        CONFIG.idempotency_persist,
+    );
+    if CONFIG.idempotency_persist {
+        for row in get_idempotency(&pool, now).await {
+            idempotency.restore(&row.app, row.api, row.key, row.count, row.expires, now);
+        }
+    }
+
     ServiceContext {
         apps: AllApp {
             set: Arc::new(RwLock::new(apps)),
@@ -116,6 +297,21 @@ pub async fn init() -> ServiceContext {
         wait_app: WaitApp::new(HashSet::new()),
         wait_api: WaitApi::new(HashMap::new()),
         wait_record: WaitRecord::new(HashMap::new()),
+        hub: Hub::default(),
+        windows: AllWindow::default(),
+        alerts: AllAlert::new(alerts),
+        quotas,
+        limits,
+        visitors: AllVisitor::new(sketches, salt, now),
+        labels,
+        histograms,
+        gauges,
+        counts: AllCount::new(counts),
+        kv,
+        flags,
+        watches: AllWatch::default(),
+        idempotency,
+        started: now,
     }
 }
 
@@ -149,4 +345,79 @@ pub struct ServiceContext {
     ///
     /// Waiting for new records to be added
     pub wait_record: WaitRecord,
+
+    /// 实时推送通道
+    ///
+    /// Live push channels
+    pub hub: Hub,
+
+    /// 滑动窗口计数
+    ///
+    /// Sliding window counts
+    pub windows: AllWindow,
+
+    /// 告警规则
+    ///
+    /// Alert rules
+    pub alerts: AllAlert,
+
+    /// 配额
+    ///
+    /// Quotas
+    pub quotas: AllQuota,
+
+    /// 限流器
+    ///
+    /// Rate limiters
+    pub limits: AllLimit,
+
+    /// 访客草图
+    ///
+    /// Visitor sketches
+    pub visitors: AllVisitor,
+
+    /// 标签组合的调用次数
+    ///
+    /// Number of calls of label combinations
+    pub labels: AllLabel,
+
+    /// 数值直方图
+    ///
+    /// Value histograms
+    pub histograms: AllHistogram,
+
+    /// 仪表
+    ///
+    /// Gauges
+    pub gauges: AllGauge,
+
+    /// 以任意 key 记录的计数
+    ///
+    /// Counters keyed by arbitrary keys
+    pub counts: AllCount,
+
+    /// 键值存储
+    ///
+    /// Key-value store
+    pub kv: AllKv,
+
+    /// 功能开关
+    ///
+    /// Feature flags
+    pub flags: AllFlag,
+
+    /// 被监听的值的版本号
+    ///
+    /// Versions of watched values
+    pub watches: AllWatch,
+
+    /// 最近的幂等键
+    ///
+    /// Recent idempotency keys
+    pub idempotency: AllIdempotency,
+
+    /// 启动时间
+    ///
+    /// Startup time
+    pub started: i64,
 }
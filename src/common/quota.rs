@@ -0,0 +1,282 @@
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+use time::{Date, Month, OffsetDateTime};
+
+use crate::model::vo::quota::QuotaVO;
+
+/// 配额周期
+///
+/// Quota period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Month,
+}
+
+impl Period {
+    pub fn parse(period: &str) -> Option<Self> {
+        match period {
+            "day" => Some(Period::Day),
+            "month" => Some(Period::Month),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Month => "month",
+        }
+    }
+
+    /// 包含 `now` 的周期的起止时间 (UTC)
+    ///
+    /// Start and end of the period containing `now` (UTC)
+    pub fn bounds(&self, now: i64) -> (i64, i64) {
+        match self {
+            Period::Day => {
+                let start = now - now.rem_euclid(24 * 60 * 60);
+                (start, start + 24 * 60 * 60)
+            }
+            Period::Month => {
+                let date = OffsetDateTime::from_unix_timestamp(now).unwrap().date();
+                let (year, month) = match date.month() {
+                    Month::December => (date.year() + 1, Month::January),
+                    month => (date.year(), month.next()),
+                };
+                let start = date.replace_day(1).unwrap();
+                let end = Date::from_calendar_date(year, month, 1).unwrap();
+                (
+                    start.midnight().assume_utc().unix_timestamp(),
+                    end.midnight().assume_utc().unix_timestamp(),
+                )
+            }
+        }
+    }
+}
+
+struct State {
+    /// 当前周期的结束时间
+    ///
+    /// End of the current period
+    end: i64,
+    used: i64,
+}
+
+/// 配额及其在当前周期内的用量
+///
+/// A quota and its usage in the current period
+pub struct Usage {
+    pub limit: i64,
+    pub period: Period,
+    state: Mutex<State>,
+}
+
+impl Usage {
+    /// `used` 为 `now` 所在周期内已有的调用次数
+    ///
+    /// `used` is the number of calls already made in the period containing `now`
+    pub fn new(limit: i64, period: Period, now: i64, used: i64) -> Self {
+        Self {
+            limit,
+            period,
+            state: Mutex::new(State {
+                end: period.bounds(now).1,
+                used,
+            }),
+        }
+    }
+
+    fn to_vo(&self, app: &str, api: &str, state: &State) -> QuotaVO {
+        QuotaVO {
+            app: app.to_owned(),
+            api: (!api.is_empty()).then(|| api.to_owned()),
+            limit: self.limit,
+            period: self.period.as_str(),
+            used: state.used,
+            reset: state.end,
+        }
+    }
+}
+
+type QuotaApi = HashMap<String, Arc<Usage>>;
+
+/// 记录所有配额, 空 api 表示整个 app 的配额
+///
+/// Record all quotas, an empty api is the quota of the whole app
+#[derive(Default)]
+pub struct AllQuota {
+    map: Arc<RwLock<HashMap<String, QuotaApi>>>,
+}
+
+impl AllQuota {
+    /// 添加或替换配额
+    ///
+    /// Add or replace a quota
+    pub fn set(&self, app: &str, api: &str, usage: Usage) {
+        self.map
+            .write()
+            .entry(app.to_owned())
+            .or_default()
+            .insert(api.to_owned(), Arc::new(usage));
+    }
+
+    /// 删除配额
+    ///
+    /// Remove a quota
+    pub fn remove(&self, app: &str, api: &str) -> bool {
+        let mut map = self.map.write();
+        let removed = map
+            .get_mut(app)
+            .is_some_and(|apis| apis.remove(api).is_some());
+        if map.get(app).is_some_and(|apis| apis.is_empty()) {
+            map.remove(app);
+        }
+        removed
+    }
+
+    /// 获取配额及其用量
+    ///
+    /// Get a quota and its usage
+    pub fn get(&self, app: &str, api: &str, now: i64) -> Option<QuotaVO> {
+        let usage = self.map.read().get(app)?.get(api)?.clone();
+        let mut state = usage.state.lock();
+        roll(&usage, &mut state, now);
+        Some(usage.to_vo(app, api, &state))
+    }
+
+    /// 获取所有配额及其用量
+    ///
+    /// Get all quotas and their usage
+    pub fn get_all(&self, now: i64) -> Vec<QuotaVO> {
+        let map = self.map.read();
+        let mut quotas: Vec<QuotaVO> = map
+            .iter()
+            .flat_map(|(app, apis)| {
+                apis.iter().map(move |(api, usage)| {
+                    let mut state = usage.state.lock();
+                    roll(usage, &mut state, now);
+                    usage.to_vo(app, api, &state)
+                })
+            })
+            .collect();
+        quotas.sort_by(|a, b| (&a.app, &a.api).cmp(&(&b.app, &b.api)));
+        quotas
+    }
+
+    /// 消耗 api 及其 app 的配额, 任意一个用尽时不消耗并返回该配额
+    ///
+    /// Consume the quotas of the api and its app, nothing is consumed if either is exhausted
+    pub fn consume(&self, app: &str, api: &str, now: i64) -> Result<(), QuotaVO> {
+        let usages: Vec<(&str, Arc<Usage>)> = {
+            let map = self.map.read();
+            let apis = match map.get(app) {
+                Some(apis) => apis,
+                None => return Ok(()),
+            };
+            [api, ""]
+                .into_iter()
+                .filter_map(|key| apis.get(key).map(|usage| (key, usage.clone())))
+                .collect()
+        };
+
+        // 固定按 api, app 的顺序加锁
+        //
+        // Always lock in the order of api, app
+        let mut states: Vec<_> = usages.iter().map(|(_, usage)| usage.state.lock()).collect();
+        for ((key, usage), state) in usages.iter().zip(states.iter_mut()) {
+            roll(usage, state, now);
+            if state.used >= usage.limit {
+                return Err(usage.to_vo(app, key, state));
+            }
+        }
+        states.iter_mut().for_each(|state| state.used += 1);
+        Ok(())
+    }
+}
+
+/// 进入新的周期时重置用量
+///
+/// Reset the usage when a new period starts
+fn roll(usage: &Usage, state: &mut State, now: i64) {
+    if now >= state.end {
+        state.end = usage.period.bounds(now).1;
+        state.used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn ts(date: OffsetDateTime) -> i64 {
+        date.unix_timestamp()
+    }
+
+    #[test]
+    fn bounds() {
+        let now = ts(datetime!(2024-02-29 23:59:59 UTC));
+        assert_eq!(
+            Period::Day.bounds(now),
+            (
+                ts(datetime!(2024-02-29 0:00 UTC)),
+                ts(datetime!(2024-03-01 0:00 UTC))
+            )
+        );
+        assert_eq!(
+            Period::Month.bounds(now),
+            (
+                ts(datetime!(2024-02-01 0:00 UTC)),
+                ts(datetime!(2024-03-01 0:00 UTC))
+            )
+        );
+        // 12 月的周期在下一年结束, 周期的结束时间属于下一个周期
+        //
+        // A December period ends in the next year, the end belongs to the next period
+        let december = ts(datetime!(2023-12-31 12:00 UTC));
+        let (_, end) = Period::Month.bounds(december);
+        assert_eq!(end, ts(datetime!(2024-01-01 0:00 UTC)));
+        assert_eq!(Period::Month.bounds(end).0, end);
+        assert_eq!(Period::Day.bounds(end).0, end);
+    }
+
+    #[test]
+    fn consume() {
+        let quotas = AllQuota::default();
+        let now = ts(datetime!(2024-01-31 12:00 UTC));
+        quotas.set("app", "", Usage::new(3, Period::Month, now, 1));
+        quotas.set("app", "api", Usage::new(10, Period::Day, now, 0));
+
+        // app 的配额先用尽, api 的用量不会增加
+        //
+        // The app quota runs out first, the api usage is not increased
+        assert!(quotas.consume("app", "api", now).is_ok());
+        assert!(quotas.consume("app", "other", now).is_ok());
+        let exhausted = quotas.consume("app", "api", now).unwrap_err();
+        assert_eq!((exhausted.api, exhausted.used), (None, 3));
+        assert_eq!(quotas.get("app", "api", now).unwrap().used, 1);
+
+        // 进入新的周期后重置
+        //
+        // Reset in the next period
+        let next = ts(datetime!(2024-02-01 0:00 UTC));
+        assert!(quotas.consume("app", "api", next).is_ok());
+        let app = quotas.get("app", "", next).unwrap();
+        assert_eq!(
+            (app.used, app.reset),
+            (1, ts(datetime!(2024-03-01 0:00 UTC)))
+        );
+        assert_eq!(quotas.get("app", "api", next).unwrap().used, 1);
+
+        // 配额为 0 时拒绝所有调用
+        //
+        // A zero quota rejects all calls
+        quotas.set("app", "api", Usage::new(0, Period::Day, next, 0));
+        assert!(quotas.consume("app", "api", next).is_err());
+        assert!(quotas.consume("free", "api", next).is_ok());
+    }
+}
@@ -64,6 +64,71 @@ impl WaitRecord {
             .or_insert(Arc::new(AtomicI64::new(1)));
     }
 
+    /// 获取 app 下各 api 上一秒的调用次数
+    ///
+    /// Get the number of calls to each api of the app in the last second
+    pub fn get_rate(&self, app: &str) -> HashMap<String, i64> {
+        let time = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+            - 1;
+
+        let record_api = match self.map.read().get(app) {
+            Some(record_api) => record_api.clone(),
+            None => return HashMap::new(),
+        };
+        let record_api = record_api.read();
+        record_api
+            .iter()
+            .filter_map(|(api, record)| {
+                record
+                    .read()
+                    .get(&time)
+                    .map(|count| (api.to_owned(), count.load(Ordering::Relaxed)))
+            })
+            .collect()
+    }
+
+    /// 获取等待中的自 `since` 起的调用次数, 未指定 api 时为整个 app 的调用次数
+    ///
+    /// Get the pending number of calls since `since`, or of the whole app without an api
+    pub fn sum_since(&self, app: &str, api: Option<&str>, since: i64) -> i64 {
+        let record_api = match self.map.read().get(app) {
+            Some(record_api) => record_api.clone(),
+            None => return 0,
+        };
+        let record_api = record_api.read();
+        record_api
+            .iter()
+            .filter(|(name, _)| api.is_none_or(|api| api == name.as_str()))
+            .map(|(_, record)| {
+                record
+                    .read()
+                    .iter()
+                    .filter(|(time, _)| **time >= since)
+                    .map(|(_, count)| count.load(Ordering::Relaxed))
+                    .sum::<i64>()
+            })
+            .sum()
+    }
+
+    /// 获取等待新增的记录数量, 每个 api 每秒为一条
+    ///
+    /// Get the number of records waiting to be added, one per api per second
+    pub fn pending(&self) -> usize {
+        self.map
+            .read()
+            .values()
+            .map(|apis| {
+                apis.read()
+                    .values()
+                    .map(|times| times.read().len())
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
     /// 获取所有需要添加的记录并清空 map
     ///
     /// Get all records that need to be added and clear the map
@@ -0,0 +1,370 @@
+use std::{
+    collections::{hash_map::RandomState, BTreeMap},
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+};
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+use sha2::{Digest, Sha256};
+
+/// 寄存器索引的位数, 标准误差约为 1.04 / sqrt(2^14) = 0.81%
+///
+/// Bits of the register index, the standard error is about 1.04 / sqrt(2^14) = 0.81%
+const PRECISION: u32 = 14;
+
+const REGISTERS: usize = 1 << PRECISION;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// 时间戳所在的日期 (UTC), 为当天 0 点的时间戳
+///
+/// The day (UTC) of the timestamp, as the timestamp of its midnight
+pub fn day(time: i64) -> i64 {
+    time - time.rem_euclid(DAY)
+}
+
+/// HyperLogLog 草图
+///
+/// HyperLogLog sketch
+#[derive(Clone)]
+pub struct Sketch {
+    registers: Vec<u8>,
+}
+
+impl Default for Sketch {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; REGISTERS],
+        }
+    }
+}
+
+impl Sketch {
+    /// 从数据库中的字节恢复, 长度不符时返回 None
+    ///
+    /// Restore from the bytes in the database, None if the length does not match
+    pub fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        (bytes.len() == REGISTERS).then_some(Self { registers: bytes })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.registers
+    }
+
+    pub fn add(&mut self, hash: u64) {
+        let index = (hash >> (64 - PRECISION)) as usize;
+        let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Sketch) {
+        self.registers
+            .iter_mut()
+            .zip(other.registers.iter())
+            .for_each(|(a, b)| *a = (*a).max(*b));
+    }
+
+    /// 估算不同访客的数量
+    ///
+    /// Estimate the number of distinct visitors
+    pub fn count(&self) -> i64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        // 基数较小时使用线性计数
+        //
+        // Use linear counting for small cardinalities
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as i64
+        } else {
+            estimate.round() as i64
+        }
+    }
+}
+
+/// 计算访客标识的哈希值, 与进程无关, 可与数据库中的草图合并
+///
+/// Hash a visitor identifier, independent of the process so it can be merged with sketches in the database
+pub fn hash(visitor: &[u8]) -> u64 {
+    let digest = Sha256::digest(visitor);
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
+/// 每天轮换的盐, 以及是否未写入数据库
+///
+/// A salt rotated every day, and whether it is not yet written to the database
+struct Salt {
+    day: i64,
+    bytes: [u8; 16],
+    dirty: bool,
+}
+
+impl Salt {
+    fn new(day: i64) -> Self {
+        let mut bytes = [0; 16];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&RandomState::new().build_hasher().finish().to_be_bytes());
+        }
+        Self {
+            day,
+            bytes,
+            dirty: true,
+        }
+    }
+
+    /// 从数据库中的字节恢复, 长度不符时重新生成
+    ///
+    /// Restore from the bytes in the database, a new one is generated if the length does not match
+    fn restore(day: i64, bytes: Option<Vec<u8>>) -> Self {
+        match bytes.and_then(|bytes| bytes.try_into().ok()) {
+            Some(bytes) => Self {
+                day,
+                bytes,
+                dirty: false,
+            },
+            None => Self::new(day),
+        }
+    }
+}
+
+/// 一个 api 每天的草图, 以及是否有未写入数据库的改动
+///
+/// Daily sketches of an api, and whether they have changes not yet written to the database
+type Daily = Arc<Mutex<BTreeMap<i64, (Sketch, bool)>>>;
+
+type VisitorApi = Arc<RwLock<HashMap<String, Daily>>>;
+
+/// 记录所有 api 的访客草图
+///
+/// Record the visitor sketches of all apis
+pub struct AllVisitor {
+    map: Arc<RwLock<HashMap<String, VisitorApi>>>,
+    salt: Mutex<Salt>,
+}
+
+impl AllVisitor {
+    /// `sketches` 为数据库中当天的草图, `salt` 为数据库中当天的盐
+    ///
+    /// `sketches` are the sketches of the current day in the database, `salt` is the salt of the current day in the database
+    pub fn new(
+        sketches: Vec<(String, String, i64, Sketch)>,
+        salt: Option<Vec<u8>>,
+        now: i64,
+    ) -> Self {
+        let visitors = Self {
+            map: Arc::new(RwLock::new(HashMap::new())),
+            salt: Mutex::new(Salt::restore(day(now), salt)),
+        };
+        for (app, api, day, sketch) in sketches {
+            visitors
+                .daily(&app, &api)
+                .lock()
+                .insert(day, (sketch, false));
+        }
+        visitors
+    }
+
+    fn daily(&self, app: &str, api: &str) -> Daily {
+        if let Some(daily) = self
+            .map
+            .read()
+            .get(app)
+            .and_then(|apis| apis.read().get(api).cloned())
+        {
+            return daily;
+        }
+        let apis = self.map.write().entry(app.to_owned()).or_default().clone();
+        let daily = apis.write().entry(api.to_owned()).or_default().clone();
+        daily
+    }
+
+    /// 记录一个访客
+    ///
+    /// Record a visitor
+    pub fn add(&self, app: &str, api: &str, hash: u64, now: i64) {
+        let daily = self.daily(app, api);
+        let mut daily = daily.lock();
+        let (sketch, dirty) = daily.entry(day(now)).or_default();
+        sketch.add(hash);
+        *dirty = true;
+    }
+
+    /// 由 ip 与 user agent 生成访客的哈希值, 盐每天轮换, 因此生成的访客只在同一天内可区分,
+    /// 同一访客在不同日期会被重复计数
+    ///
+    /// Derive the hash of a visitor from the ip and user agent, the salt is rotated every day
+    /// so derived visitors are distinct only within a day, the same visitor is counted again on different days
+    pub fn derive(&self, ip: &str, user_agent: &str, now: i64) -> u64 {
+        let mut salt = self.salt.lock();
+        if salt.day != day(now) {
+            *salt = Salt::new(day(now));
+        }
+        let mut visitor = salt.bytes.to_vec();
+        visitor.extend_from_slice(ip.as_bytes());
+        visitor.push(0);
+        visitor.extend_from_slice(user_agent.as_bytes());
+        hash(&visitor)
+    }
+
+    /// 获取未写入数据库的盐并清除标记
+    ///
+    /// Get the salt not yet written to the database and clear its flag
+    pub fn get_salt_dirty(&self) -> Option<(i64, [u8; 16])> {
+        let mut salt = self.salt.lock();
+        salt.dirty.then(|| {
+            salt.dirty = false;
+            (salt.day, salt.bytes)
+        })
+    }
+
+    /// 合并内存中 `from` 至 `to` 日期内的草图, 未指定 api 时合并整个 app
+    ///
+    /// Merge the sketches in memory from `from` to `to` days, or of the whole app without an api
+    pub fn merge_into(
+        &self,
+        sketch: &mut Sketch,
+        app: &str,
+        api: Option<&str>,
+        from: i64,
+        to: i64,
+    ) {
+        let apis = match self.map.read().get(app) {
+            Some(apis) => apis.clone(),
+            None => return,
+        };
+        let apis = apis.read();
+        apis.iter()
+            .filter(|(name, _)| api.is_none_or(|api| api == name.as_str()))
+            .for_each(|(_, daily)| {
+                daily
+                    .lock()
+                    .range(from..=to)
+                    .for_each(|(_, (other, _))| sketch.merge(other));
+            });
+    }
+
+    /// 获取所有有改动的草图并清除改动标记, 同时丢弃今天以前已写入的草图
+    ///
+    /// Get all changed sketches and clear their flags, sketches before today that were written are dropped
+    pub fn get_dirty(&self, now: i64) -> Vec<(String, String, i64, Sketch)> {
+        let today = day(now);
+        let mut dirty = vec![];
+        let apps: Vec<(String, VisitorApi)> = self
+            .map
+            .read()
+            .iter()
+            .map(|(app, apis)| (app.to_owned(), apis.clone()))
+            .collect();
+        for (app, apis) in apps {
+            let apis: Vec<(String, Daily)> = apis
+                .read()
+                .iter()
+                .map(|(api, daily)| (api.to_owned(), daily.clone()))
+                .collect();
+            for (api, daily) in apis {
+                let mut daily = daily.lock();
+                // 本次才写入的草图留到下次同步再丢弃, 以免写入前查询不到
+                //
+                // Sketches written by this sync are dropped by the next one,
+                // so they can still be queried before they are written
+                daily.retain(|day, (_, changed)| *day >= today || *changed);
+                for (day, (sketch, changed)) in daily.iter_mut() {
+                    if *changed {
+                        dirty.push((app.to_owned(), api.to_owned(), *day, sketch.clone()));
+                        *changed = false;
+                    }
+                }
+            }
+        }
+        dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sketch(range: std::ops::Range<u64>) -> Sketch {
+        let mut sketch = Sketch::default();
+        range.for_each(|i| sketch.add(hash(&i.to_be_bytes())));
+        sketch
+    }
+
+    fn assert_close(count: i64, expected: i64) {
+        let error = (count - expected).abs() as f64 / expected as f64;
+        assert!(error < 0.03, "estimated {count}, expected {expected}");
+    }
+
+    #[test]
+    fn estimate() {
+        assert_eq!(Sketch::default().count(), 0);
+        assert_eq!(sketch(0..10).count(), 10);
+        assert_close(sketch(0..1000).count(), 1000);
+        assert_close(sketch(0..100_000).count(), 100_000);
+        // 重复的访客不会增加计数
+        //
+        // Repeated visitors do not increase the count
+        let mut repeated = sketch(0..1000);
+        (0..1000u64).for_each(|i| repeated.add(hash(&i.to_be_bytes())));
+        assert_eq!(repeated.count(), sketch(0..1000).count());
+    }
+
+    #[test]
+    fn merge() {
+        let mut merged = sketch(0..60_000);
+        merged.merge(&sketch(40_000..100_000));
+        assert_eq!(merged.as_bytes(), sketch(0..100_000).as_bytes());
+        assert_close(merged.count(), 100_000);
+
+        let restored = Sketch::from_bytes(merged.as_bytes().to_vec()).unwrap();
+        assert_eq!(restored.count(), merged.count());
+        assert!(Sketch::from_bytes(vec![0; 16]).is_none());
+    }
+
+    #[test]
+    fn salt() {
+        let now = 1_700_000_000;
+        let visitors = AllVisitor::new(vec![], None, now);
+        let hash = visitors.derive("127.0.0.1", "curl", now);
+        assert_ne!(hash, visitors.derive("127.0.0.2", "curl", now));
+        let (day, salt) = visitors.get_salt_dirty().unwrap();
+        assert!(visitors.get_salt_dirty().is_none());
+
+        // 恢复的盐生成相同的访客, 第二天重新生成
+        //
+        // A restored salt derives the same visitor, a new one is generated the next day
+        let restored = AllVisitor::new(vec![], Some(salt.to_vec()), now + 60);
+        assert!(restored.get_salt_dirty().is_none());
+        assert_eq!(restored.derive("127.0.0.1", "curl", now + 60), hash);
+        assert_ne!(restored.derive("127.0.0.1", "curl", now + DAY), hash);
+        assert_eq!(restored.get_salt_dirty().unwrap().0, day + DAY);
+    }
+
+    #[test]
+    fn dirty_kept_until_written() {
+        let now = 1_700_000_000;
+        let visitors = AllVisitor::new(vec![], None, now);
+        visitors.add("app1", "api1", hash(b"user1"), now);
+        let count = |to: i64| {
+            let mut sketch = Sketch::default();
+            visitors.merge_into(&mut sketch, "app1", None, day(now), to);
+            sketch.count()
+        };
+
+        // 跨过午夜后, 昨天的草图在写入前后都还能查询到, 下次同步才丢弃
+        //
+        // After midnight yesterday's sketch can still be queried around its write,
+        // it is dropped by the next sync
+        let tomorrow = now + DAY;
+        let dirty = visitors.get_dirty(tomorrow);
+        assert_eq!((dirty.len(), dirty[0].2), (1, day(now)));
+        assert_eq!(count(day(tomorrow)), 1);
+        assert!(visitors.get_dirty(tomorrow).is_empty());
+        assert_eq!(count(day(tomorrow)), 0);
+    }
+}
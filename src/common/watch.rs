@@ -0,0 +1,194 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use hashbrown::HashMap;
+use parking_lot::RwLock;
+use tokio::sync::watch::{self, Receiver, Sender};
+
+use crate::{common::kv::Kind, util};
+
+/// 可被监听的值
+///
+/// A value that can be watched
+pub enum Target<'a> {
+    Api(&'a str, &'a str),
+    Count(&'a str),
+    Kv(Kind, &'a str),
+}
+
+impl Target<'_> {
+    fn key(&self) -> String {
+        match self {
+            Target::Api(app, api) => format!("api/{}/{}", app, api),
+            Target::Count(key) => format!("count/{}", key),
+            Target::Kv(kind, key) => format!("{}/{}", kind.as_str(), key),
+        }
+    }
+}
+
+/// 被监听的值的推送通道, 以及通道被移除后保留的版本号
+///
+/// Channels of watched values, and the versions kept after their channels are removed
+#[derive(Default)]
+struct Channels {
+    senders: HashMap<String, Sender<u64>>,
+    versions: HashMap<String, u64>,
+}
+
+/// 保留的版本号的最大数量, 超出时移除最旧的版本号, 这些值再次被监听时会报告一次改动
+///
+/// Maximum number of kept versions, the oldest ones are removed beyond it
+/// and those values report one change when watched again
+const MAX_VERSIONS: usize = 100_000;
+
+/// 记录被监听的值的版本号, 只保存在内存中
+///
+/// 版本号取自全局递增的序号, 每次改动都会递增. 通道被移除后仍保留该值的版本号并继续更新,
+/// 因此客户端用上次的版本号再次监听时不会误报改动. 从未被监听的值以当前序号作为版本号,
+/// 不会小于该值上次改动的序号. 序号从启动时的微秒时间戳开始, 重启后也不会重复.
+///
+/// Record the versions of watched values, kept in memory only
+///
+/// Versions come from a global sequence increased on every change. The version of a value is kept
+/// and updated after its channel is removed, so watching again with the last version does not report
+/// a false change. A value never watched takes the current sequence, which is never below its last change.
+/// The sequence starts from the microsecond timestamp at startup so it does not repeat after a restart.
+pub struct AllWatch {
+    map: Arc<RwLock<Channels>>,
+    seq: AtomicU64,
+}
+
+impl Default for AllWatch {
+    fn default() -> Self {
+        Self {
+            map: Arc::new(RwLock::new(Channels::default())),
+            seq: AtomicU64::new((util::now_f64() * 1_000_000.0) as u64),
+        }
+    }
+}
+
+impl AllWatch {
+    /// 通知值已改动, 需要在改动之后调用
+    ///
+    /// Notify that the value changed, must be called after the change
+    pub fn notify(&self, target: Target) {
+        let version = self.seq.fetch_add(1, Ordering::SeqCst) + 1;
+        let map = self.map.read();
+        if map.senders.is_empty() && map.versions.is_empty() {
+            return;
+        }
+        let key = target.key();
+        if let Some(sender) = map.senders.get(&key) {
+            sender.send_replace(version);
+            return;
+        }
+        if !map.versions.contains_key(&key) {
+            return;
+        }
+        drop(map);
+        let mut map = self.map.write();
+        match map.senders.get(&key) {
+            Some(sender) => {
+                sender.send_replace(version);
+            }
+            None => {
+                if let Some(current) = map.versions.get_mut(&key) {
+                    *current = version.max(*current);
+                }
+            }
+        }
+    }
+
+    /// 监听值的版本号
+    ///
+    /// Watch the version of the value
+    pub fn subscribe(&self, target: Target) -> Receiver<u64> {
+        let key = target.key();
+        if let Some(sender) = self.map.read().senders.get(&key) {
+            return sender.subscribe();
+        }
+        let mut map = self.map.write();
+        let map = &mut *map;
+        if let Some(sender) = map.senders.get(&key) {
+            return sender.subscribe();
+        }
+        let version = map
+            .versions
+            .remove(&key)
+            .unwrap_or_else(|| self.seq.load(Ordering::SeqCst));
+        let (sender, receiver) = watch::channel(version);
+        map.senders.insert(key, sender);
+        receiver
+    }
+
+    /// 移除没有监听者的通道, 保留其版本号
+    ///
+    /// Remove the channels without watchers, keeping their versions
+    pub fn evict(&self) {
+        let mut map = self.map.write();
+        let map = &mut *map;
+        let versions = &mut map.versions;
+        map.senders.retain(|key, sender| {
+            if sender.receiver_count() > 0 {
+                return true;
+            }
+            versions.insert(key.to_owned(), *sender.borrow());
+            false
+        });
+        if versions.len() > MAX_VERSIONS {
+            let mut sorted: Vec<u64> = versions.values().copied().collect();
+            sorted.sort_unstable();
+            let min = sorted[versions.len() - MAX_VERSIONS];
+            versions.retain(|_, version| *version >= min);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_outlives_eviction() {
+        let watches = AllWatch::default();
+        let receiver = watches.subscribe(Target::Count("a"));
+        watches.notify(Target::Count("a"));
+        let version = *receiver.borrow();
+        drop(receiver);
+
+        // 其他值的改动不影响再次监听的版本号
+        //
+        // Changes of other values do not affect the version when watched again
+        watches.evict();
+        watches.notify(Target::Count("b"));
+        assert_eq!(*watches.subscribe(Target::Count("a")).borrow(), version);
+
+        // 通道被移除后的改动仍会更新版本号
+        //
+        // Changes after the channel is removed still update the version
+        watches.evict();
+        watches.notify(Target::Count("a"));
+        let receiver = watches.subscribe(Target::Count("a"));
+        assert!(*receiver.borrow() > version);
+        assert!(watches.map.read().versions.is_empty());
+    }
+
+    #[test]
+    fn max_versions() {
+        let watches = AllWatch::default();
+        for i in 0..MAX_VERSIONS + 10 {
+            let key = i.to_string();
+            watches.notify(Target::Count(&key));
+            drop(watches.subscribe(Target::Count(&key)));
+        }
+        watches.evict();
+        let map = watches.map.read();
+        assert_eq!(map.versions.len(), MAX_VERSIONS);
+        assert!(!map.versions.contains_key("count/0"));
+        assert!(map
+            .versions
+            .contains_key(&format!("count/{}", MAX_VERSIONS + 9)));
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+
+use crate::model::vo::window::WindowVO;
+
+/// 环形缓冲区, 每个槽位记录一个时间片内的调用次数
+///
+/// Ring buffer, each slot records the number of calls in one time slice
+struct Ring {
+    /// (时间片序号, 调用次数)
+    ///
+    /// (slice index, number of calls)
+    slots: Vec<(i64, i64)>,
+    /// 时间片长度 (秒)
+    ///
+    /// Slice length (seconds)
+    width: i64,
+}
+
+impl Ring {
+    fn new(len: usize, width: i64) -> Self {
+        Self {
+            slots: vec![(-1, 0); len],
+            width,
+        }
+    }
+
+    fn add(&mut self, now: i64, count: i64) {
+        let index = now / self.width;
+        let len = self.slots.len() as i64;
+        let slot = &mut self.slots[index.rem_euclid(len) as usize];
+        if slot.0 != index {
+            *slot = (index, 0);
+        }
+        slot.1 += count;
+    }
+
+    /// 最近 `slices` 个时间片 (包含当前时间片) 的调用次数之和
+    ///
+    /// Sum of the calls in the latest `slices` slices, including the current one
+    fn sum(&self, now: i64, slices: i64) -> i64 {
+        let index = now / self.width;
+        self.slots
+            .iter()
+            .filter(|(i, _)| *i <= index && *i > index - slices)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// api 的滑动窗口计数
+///
+/// 最近 60 秒按秒记录, 最近 24 小时按分钟记录
+///
+/// Sliding window counts of an api
+///
+/// The last 60 seconds are kept per second, the last 24 hours per minute
+pub struct Window {
+    seconds: Ring,
+    minutes: Ring,
+}
+
+impl Window {
+    fn new() -> Self {
+        Self {
+            seconds: Ring::new(60, 1),
+            // 多一个槽位存放当前未满的分钟
+            //
+            // One more slot for the current partial minute
+            minutes: Ring::new(24 * 60 + 1, 60),
+        }
+    }
+
+    fn add(&mut self, now: i64, count: i64) {
+        self.seconds.add(now, count);
+        self.minutes.add(now, count);
+    }
+
+    /// 最近 `secs` 秒的调用次数, 超过 60 秒时按分钟计算, 为完整的分钟加上当前未满的分钟
+    ///
+    /// Number of calls in the last `secs` seconds, beyond 60 seconds it is counted per minute,
+    /// as the full minutes plus the current partial one
+    pub fn sum(&self, now: i64, secs: i64) -> i64 {
+        match secs <= 60 {
+            true => self.seconds.sum(now, secs),
+            false => self.minutes.sum(now, (secs + 59) / 60 + 1),
+        }
+    }
+
+    pub fn get(&self, now: i64) -> WindowVO {
+        WindowVO {
+            m1: self.sum(now, 60),
+            m5: self.sum(now, 5 * 60),
+            h1: self.sum(now, 60 * 60),
+            d1: self.sum(now, 24 * 60 * 60),
+            // 按最近 10 个完整的秒计算
+            //
+            // Computed over the last 10 complete seconds
+            rps: self.seconds.sum(now - 1, 10) as f64 / 10.0,
+        }
+    }
+}
+
+type WindowApi = Arc<RwLock<HashMap<String, Arc<Mutex<Window>>>>>;
+
+/// 记录所有 api 的滑动窗口计数
+///
+/// Record the sliding window counts of all apis
+#[derive(Default)]
+pub struct AllWindow {
+    map: Arc<RwLock<HashMap<String, WindowApi>>>,
+}
+
+impl AllWindow {
+    /// 记录 api 的调用
+    ///
+    /// Record calls to the api
+    pub fn add(&self, app: &str, api: &str, now: i64, count: i64) {
+        if !self.map.read().contains_key(app) {
+            self.map.write().entry(app.to_owned()).or_default();
+        }
+        let window_api = { self.map.read().get(app).unwrap().clone() };
+        if !window_api.read().contains_key(api) {
+            window_api
+                .write()
+                .entry(api.to_owned())
+                .or_insert_with(|| Arc::new(Mutex::new(Window::new())));
+        }
+        let window = { window_api.read().get(api).unwrap().clone() };
+        window.lock().add(now, count);
+    }
+
+    /// 获取 api 最近 `secs` 秒的调用次数, 未指定 api 时为整个 app 的调用次数
+    ///
+    /// Get the number of calls to the api in the last `secs` seconds, or to the whole app without an api
+    pub fn sum(&self, app: &str, api: Option<&str>, now: i64, secs: i64) -> i64 {
+        match api {
+            Some(api) => self
+                .get_window(app, api)
+                .map(|window| window.lock().sum(now, secs))
+                .unwrap_or_default(),
+            None => {
+                let window_api = match self.map.read().get(app) {
+                    Some(window_api) => window_api.clone(),
+                    None => return 0,
+                };
+                let window_api = window_api.read();
+                window_api
+                    .values()
+                    .map(|window| window.lock().sum(now, secs))
+                    .sum()
+            }
+        }
+    }
+
+    /// 获取 api 的滑动窗口计数
+    ///
+    /// Get the sliding window counts of the api
+    pub fn get_api(&self, app: &str, api: &str, now: i64) -> WindowVO {
+        self.get_window(app, api)
+            .map(|window| window.lock().get(now))
+            .unwrap_or_default()
+    }
+
+    fn get_window(&self, app: &str, api: &str) -> Option<Arc<Mutex<Window>>> {
+        let window_api = { self.map.read().get(app)?.clone() };
+        let window = window_api.read().get(api)?.clone();
+        Some(window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum() {
+        let mut window = Window::new();
+        let start = 1_700_000_000 - 1_700_000_000 % 60;
+        // 24 小时前的一次调用, 以及之后每分钟一次调用
+        //
+        // One call 24 hours ago, then one call every minute
+        window.add(start - 24 * 60 * 60, 100);
+        window.add(start, 1);
+        assert_eq!(window.sum(start, 24 * 60 * 60), 101);
+        assert_eq!(window.sum(start + 60, 24 * 60 * 60), 1);
+        for minute in 1..10 {
+            window.add(start + minute * 60, 1);
+        }
+        let now = start + 9 * 60 + 30;
+
+        assert_eq!(window.sum(now, 60), 1);
+        assert_eq!(window.seconds.sum(now - 30, 1), 1);
+        // 5 个完整的分钟加上当前未满的分钟
+        //
+        // 5 full minutes plus the current partial one
+        assert_eq!(window.sum(now, 5 * 60), 6);
+        assert_eq!(window.sum(now, 60 * 60), 10);
+        assert_eq!(window.get(now).d1, 10);
+    }
+}
@@ -1,23 +1,136 @@
-use std::path::PathBuf;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
 
+use anyhow::{anyhow, bail, Context, Result};
+use axum::http::HeaderValue;
+use hashbrown::HashMap;
 use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use serde::Deserialize;
+use serde_json::{Map, Value};
+use time::UtcOffset;
+use tracing::{error, info, warn};
 
-pub static CONFIG: Lazy<ApplicationConfig> = Lazy::new(ApplicationConfig::load);
+use crate::{log, model::vo::config::ReloadVO, util};
+
+pub static CONFIG: Lazy<ApplicationConfig> = Lazy::new(|| {
+    ApplicationConfig::load().unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {:#}", e);
+        std::process::exit(2);
+    })
+});
+
+/// 运行中的配置, 重新加载时只更新可以在运行时生效的配置项, 其余配置项保持启动时的值
+///
+/// The running configuration, a reload only updates the keys that can take effect at runtime,
+/// the others keep their values from startup
+static CURRENT: Lazy<RwLock<Arc<ApplicationConfig>>> =
+    Lazy::new(|| RwLock::new(Arc::new(CONFIG.clone())));
+
+/// 获取运行中的配置
+///
+/// Get the running configuration
+pub fn current() -> Arc<ApplicationConfig> {
+    CURRENT.read().clone()
+}
+
+/// 环境变量前缀, 如 `APIREC_PORT`
+///
+/// Environment variable prefix, such as `APIREC_PORT`
+const ENV_PREFIX: &str = "APIREC_";
+
+/// 可以由环境变量与命令行参数覆盖的配置项
+///
+/// Configuration keys that can be overridden by environment variables and command line arguments
+const KEYS: &[&str] = &[
+    "server_name",
+    "host",
+    "port",
+    "ipv6_only",
+    "listen_tcp",
+    "unix_socket",
+    "unix_socket_mode",
+    "tls_cert",
+    "tls_key",
+    "tls_min_version",
+    "log_level",
+    "log_split",
+    "log_dir",
+    "log_target",
+    "log_format",
+    "log_timezone",
+    "max_log_files",
+    "sync_interval",
+    "data_dir",
+    "admin_import",
+    "snapshot_dir",
+    "snapshot_interval",
+    "snapshot_keep",
+    "stream_tick",
+    "alert_interval",
+    "alert_webhook",
+    "alert_retries",
+    "label_limit",
+    "limit_keys",
+    "cors_origins",
+    "cors_admin_origins",
+    "cors_app_origins",
+    "cors_credentials",
+    "cors_max_age",
+    "idempotency_ttl",
+    "idempotency_capacity",
+    "idempotency_persist",
+];
 
 /// 配置
 ///
 /// Configuration
 #[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ConfigFile {
     /// 服务名称
     ///
     /// Service name
     pub server_name: Option<String>,
+    /// 监听地址, 为 `::` 时同时监听 IPv4 与 IPv6
+    ///
+    /// Listen address, both IPv4 and IPv6 are served with `::`
+    pub host: Option<String>,
     /// 服务端口
     ///
     /// Service port
     pub port: Option<u16>,
+    /// 监听 IPv6 地址时不接受 IPv4 连接
+    ///
+    /// Don't accept IPv4 connections when listening on an IPv6 address
+    pub ipv6_only: Option<bool>,
+    /// 是否监听 TCP 端口, 为 false 时只监听 Unix 套接字
+    ///
+    /// Whether to listen on the TCP port, only the Unix socket is served when false
+    pub listen_tcp: Option<bool>,
+    /// Unix 套接字路径
+    ///
+    /// Unix socket path
+    pub unix_socket: Option<String>,
+    /// Unix 套接字文件的权限 (八进制)
+    ///
+    /// Permissions of the Unix socket file (octal)
+    pub unix_socket_mode: Option<String>,
+    /// TLS 证书链文件 (PEM), 与私钥同时设置时提供 HTTPS
+    ///
+    /// TLS certificate chain file (PEM), HTTPS is served when set together with the key
+    pub tls_cert: Option<String>,
+    /// TLS 私钥文件 (PEM)
+    ///
+    /// TLS private key file (PEM)
+    pub tls_key: Option<String>,
+    /// 最低 TLS 版本, 1.2 或 1.3
+    ///
+    /// Minimum TLS version, 1.2 or 1.3
+    pub tls_min_version: Option<String>,
     /// 日志级别
     ///
     /// Log level
@@ -26,10 +139,107 @@ pub struct ConfigFile {
     ///
     /// Log split
     pub log_split: Option<String>,
+    /// 日志目录
+    ///
+    /// Log directory
+    pub log_dir: Option<String>,
+    /// 日志输出位置, stdout, file 或 both
+    ///
+    /// Log output, stdout, file or both
+    pub log_target: Option<String>,
+    /// 日志格式, text 或 json
+    ///
+    /// Log format, text or json
+    pub log_format: Option<String>,
+    /// 日志时区, local 为系统时区, 或 UTC, +08:00 等
+    ///
+    /// Log time zone, local for the system time zone, or UTC, +08:00 and so on
+    pub log_timezone: Option<String>,
+    /// 保留的日志文件数量, 为 0 时不清理
+    ///
+    /// Number of log files to keep, never cleaned up when 0
+    pub max_log_files: Option<usize>,
     /// 同步间隔
     ///
     /// Synchronization interval
     pub sync_interval: Option<u64>,
+    /// 数据目录
+    ///
+    /// Data directory
+    pub data_dir: Option<String>,
+    /// 是否开启 `/admin/import` 接口
+    ///
+    /// Whether the `/admin/import` endpoint is enabled
+    pub admin_import: Option<bool>,
+    /// 快照目录
+    ///
+    /// Snapshot directory
+    pub snapshot_dir: Option<String>,
+    /// 定时快照间隔, 为 0 时不开启
+    ///
+    /// Scheduled snapshot interval, disabled when 0
+    pub snapshot_interval: Option<u64>,
+    /// 保留的快照数量, 至少为 1
+    ///
+    /// Number of snapshots to keep, at least 1
+    pub snapshot_keep: Option<usize>,
+    /// 实时推送间隔 (毫秒)
+    ///
+    /// Live push interval (milliseconds)
+    pub stream_tick: Option<u64>,
+    /// 告警检查间隔
+    ///
+    /// Alert check interval
+    pub alert_interval: Option<u64>,
+    /// 默认告警回调地址
+    ///
+    /// Default alert webhook url
+    pub alert_webhook: Option<String>,
+    /// 告警回调重试次数
+    ///
+    /// Alert webhook retries
+    pub alert_retries: Option<u32>,
+    /// 每个 api 最多的标签组合数量
+    ///
+    /// Maximum number of label combinations per api
+    pub label_limit: Option<usize>,
+    /// 每个限流规则最多同时记录的 key 数量
+    ///
+    /// Maximum number of keys tracked at once per rate limit rule
+    pub limit_keys: Option<usize>,
+    /// 记录调用等公开接口允许跨域请求的来源, `*` 为允许所有来源
+    ///
+    /// Origins allowed for cross-origin requests to public endpoints such as recording hits,
+    /// `*` allows all origins
+    pub cors_origins: Option<Vec<String>>,
+    /// 管理接口允许跨域请求的来源
+    ///
+    /// Origins allowed for cross-origin requests to management endpoints
+    pub cors_admin_origins: Option<Vec<String>>,
+    /// 按 app 设置公开接口允许跨域请求的来源, 代替 `cors_origins`
+    ///
+    /// Origins allowed for cross-origin requests to public endpoints per app, instead of `cors_origins`
+    pub cors_app_origins: Option<HashMap<String, Vec<String>>>,
+    /// 是否允许跨域请求携带 cookie 等凭据
+    ///
+    /// Whether cross-origin requests may carry credentials such as cookies
+    pub cors_credentials: Option<bool>,
+    /// 浏览器缓存预检请求结果的秒数
+    ///
+    /// Seconds browsers cache the result of a preflight request
+    pub cors_max_age: Option<u64>,
+    /// 幂等键保留的秒数
+    ///
+    /// Seconds an idempotency key is kept
+    pub idempotency_ttl: Option<i64>,
+    /// 每个 app 最多保留的幂等键数量
+    ///
+    /// Maximum number of idempotency keys kept per app
+    pub idempotency_capacity: Option<usize>,
+    /// 是否将幂等键写入数据库, 重启后仍然有效
+    ///
+    /// Whether to write idempotency keys to the database so they survive a restart
+    pub idempotency_persist: Option<bool>,
 }
 
 /// 配置
@@ -43,7 +253,27 @@ pub struct ApplicationConfig {
     /// 服务地址
     ///
     /// Service address
-    pub server_url: String,
+    pub server_addr: SocketAddr,
+    /// 监听 IPv6 地址时不接受 IPv4 连接
+    ///
+    /// Don't accept IPv4 connections when listening on an IPv6 address
+    pub ipv6_only: bool,
+    /// 是否监听 TCP 端口
+    ///
+    /// Whether to listen on the TCP port
+    pub listen_tcp: bool,
+    /// Unix 套接字路径
+    ///
+    /// Unix socket path
+    pub unix_socket: Option<PathBuf>,
+    /// Unix 套接字文件的权限
+    ///
+    /// Permissions of the Unix socket file
+    pub unix_socket_mode: u32,
+    /// TLS 设置, 为空时提供 HTTP
+    ///
+    /// TLS settings, HTTP is served when empty
+    pub tls: Option<TlsConfig>,
     /// 日志级别
     ///
     /// Log level
@@ -52,43 +282,642 @@ pub struct ApplicationConfig {
     ///
     /// Log split
     pub log_split: String,
-    /// 可执行文件目录
+    /// 日志目录
+    ///
+    /// Log directory
+    pub log_dir: PathBuf,
+    /// 是否输出日志到标准输出
     ///
-    /// Executable file directory
-    pub exe_dir: PathBuf,
+    /// Whether the log is written to stdout
+    pub log_stdout: bool,
+    /// 是否输出日志到文件
+    ///
+    /// Whether the log is written to files
+    pub log_file: bool,
+    /// 是否使用 JSON 格式的日志
+    ///
+    /// Whether the log is in JSON format
+    pub log_json: bool,
+    /// 日志时区, 为空时使用系统时区
+    ///
+    /// Log time zone, the system time zone is used when empty
+    pub log_timezone: Option<UtcOffset>,
+    /// 保留的日志文件数量, 为 0 时不清理
+    ///
+    /// Number of log files to keep, never cleaned up when 0
+    pub max_log_files: usize,
     /// 同步间隔
     ///
     /// Synchronization interval
     pub sync_interval: u64,
+    /// 数据目录, 存放数据库
+    ///
+    /// Data directory, where the database is kept
+    pub data_dir: PathBuf,
+    /// 是否开启 `/admin/import` 接口
+    ///
+    /// Whether the `/admin/import` endpoint is enabled
+    pub admin_import: bool,
+    /// 快照目录
+    ///
+    /// Snapshot directory
+    pub snapshot_dir: PathBuf,
+    /// 定时快照间隔
+    ///
+    /// Scheduled snapshot interval
+    pub snapshot_interval: u64,
+    /// 保留的快照数量
+    ///
+    /// Number of snapshots to keep
+    pub snapshot_keep: usize,
+    /// 实时推送间隔 (毫秒)
+    ///
+    /// Live push interval (milliseconds)
+    pub stream_tick: u64,
+    /// 告警检查间隔
+    ///
+    /// Alert check interval
+    pub alert_interval: u64,
+    /// 默认告警回调地址
+    ///
+    /// Default alert webhook url
+    pub alert_webhook: Option<String>,
+    /// 告警回调重试次数
+    ///
+    /// Alert webhook retries
+    pub alert_retries: u32,
+    /// 每个 api 最多的标签组合数量
+    ///
+    /// Maximum number of label combinations per api
+    pub label_limit: usize,
+    /// 每个限流规则最多同时记录的 key 数量
+    ///
+    /// Maximum number of keys tracked at once per rate limit rule
+    pub limit_keys: usize,
+    /// 公开接口允许跨域请求的来源
+    ///
+    /// Origins allowed for cross-origin requests to public endpoints
+    pub cors_origins: Vec<String>,
+    /// 管理接口允许跨域请求的来源
+    ///
+    /// Origins allowed for cross-origin requests to management endpoints
+    pub cors_admin_origins: Vec<String>,
+    /// 按 app 设置公开接口允许跨域请求的来源
+    ///
+    /// Origins allowed for cross-origin requests to public endpoints per app
+    pub cors_app_origins: HashMap<String, Vec<String>>,
+    /// 是否允许跨域请求携带凭据
+    ///
+    /// Whether cross-origin requests may carry credentials
+    pub cors_credentials: bool,
+    /// 浏览器缓存预检请求结果的秒数
+    ///
+    /// Seconds browsers cache the result of a preflight request
+    pub cors_max_age: u64,
+    /// 幂等键保留的秒数
+    ///
+    /// Seconds an idempotency key is kept
+    pub idempotency_ttl: i64,
+    /// 每个 app 最多保留的幂等键数量
+    ///
+    /// Maximum number of idempotency keys kept per app
+    pub idempotency_capacity: usize,
+    /// 是否将幂等键写入数据库
+    ///
+    /// Whether to write idempotency keys to the database
+    pub idempotency_persist: bool,
+    /// 除配置项以外的命令行参数, 即子命令与其参数
+    ///
+    /// Command line arguments other than configuration, i.e. the subcommand and its arguments
+    pub args: Vec<String>,
+}
+
+/// TLS 设置
+///
+/// TLS settings
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// 是否只接受 TLS 1.3
+    ///
+    /// Whether only TLS 1.3 is accepted
+    pub tls13_only: bool,
+}
+
+/// 命令行参数
+///
+/// Command line arguments
+struct Args {
+    /// `--config` 指定的配置文件
+    ///
+    /// Configuration file given by `--config`
+    config: Option<String>,
+    /// `--<配置项> <值>` 或 `--<配置项>=<值>`, 配置项中的 `-` 与 `_` 等价
+    ///
+    /// `--<key> <value>` or `--<key>=<value>`, `-` and `_` are the same in keys
+    overrides: Vec<(String, String)>,
+    /// 子命令及其参数
+    ///
+    /// The subcommand and its arguments
+    rest: Vec<String>,
+}
+
+/// 命令行子命令, 只有子命令可以使用配置项以外的参数
+///
+/// Command line subcommands, only they accept arguments other than configuration keys
+const SUBCOMMANDS: [&str; 2] = ["import", "snapshot"];
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut parsed = Args {
+            config: None,
+            overrides: vec![],
+            rest: vec![],
+        };
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            let flag = match arg.strip_prefix("--") {
+                Some(flag) => flag,
+                None => {
+                    parsed.rest.push(arg);
+                    continue;
+                }
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name.replace('-', "_"), Some(value.to_owned())),
+                None => (flag.replace('-', "_"), None),
+            };
+            // 其他参数留给子命令, 如 `--replace`
+            //
+            // Other arguments are left to the subcommand, such as `--replace`
+            if name != "config" && !KEYS.contains(&name.as_str()) {
+                parsed.rest.push(arg);
+                continue;
+            }
+            let value = match value.or_else(|| args.next_if(|arg| !arg.starts_with("--"))) {
+                Some(value) => value,
+                None => bail!("missing value for `--{}`", flag),
+            };
+            match name == "config" {
+                true => parsed.config = Some(value),
+                false => parsed.overrides.push((name, value)),
+            }
+        }
+        // 没有子命令时, 拼错的参数不能被忽略
+        //
+        // Without a subcommand, a misspelled argument must not be ignored
+        match parsed.rest.first() {
+            Some(arg) if !SUBCOMMANDS.contains(&arg.as_str()) => match arg.starts_with("--") {
+                true => bail!("unknown argument `{}`", arg),
+                false => bail!("unknown subcommand `{}`", arg),
+            },
+            _ => Ok(parsed),
+        }
+    }
+}
+
+/// 检查单个配置项的类型
+///
+/// Check the type of a single configuration key
+fn check(key: &str, value: &Value) -> Result<(), serde_json::Error> {
+    let mut map = Map::new();
+    map.insert(key.to_owned(), value.clone());
+    serde_json::from_value::<ConfigFile>(Value::Object(map)).map(|_| ())
+}
+
+/// 将环境变量或命令行参数中的值转换为配置项的类型, 优先作为字符串
+///
+/// Convert a value from an environment variable or command line argument to the type of the key,
+/// as a string first
+fn coerce(key: &str, raw: String) -> Value {
+    let string = Value::String(raw);
+    if check(key, &string).is_ok() {
+        return string;
+    }
+    serde_json::from_str(string.as_str().unwrap()).unwrap_or(string)
+}
+
+/// 解析时区偏移, 如 `UTC`, `+08:00`, `-05:30`, `+8`
+///
+/// Parse a time zone offset, such as `UTC`, `+08:00`, `-05:30`, `+8`
+fn parse_offset(value: &str) -> Option<UtcOffset> {
+    if value.eq_ignore_ascii_case("utc") || value == "Z" {
+        return Some(UtcOffset::UTC);
+    }
+    let sign = match value.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = value[1..].split_once(':').unwrap_or((&value[1..], "0"));
+    let hours: i8 = hours.parse().ok()?;
+    let minutes: i8 = minutes.parse().ok()?;
+    if !(0..60).contains(&minutes) {
+        return None;
+    }
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).ok()
+}
+
+fn invalid(key: &str, reason: &str) -> anyhow::Error {
+    anyhow!("invalid value for `{}`: {}", key, reason)
 }
 
 impl ApplicationConfig {
-    fn load() -> Self {
-        let exe_path = std::env::current_exe().expect("Failed to get current executable");
+    /// 读取配置, 优先级为: 命令行参数, `APIREC_*` 环境变量, 配置文件, 默认值
+    ///
+    /// Load the configuration, in order of precedence: command line arguments,
+    /// `APIREC_*` environment variables, the configuration file, defaults
+    fn load() -> Result<Self> {
+        let exe_path = std::env::current_exe().context("failed to get current executable")?;
         let exe_dir = exe_path.parent().unwrap();
-        let config_file = exe_dir.join("config.toml");
-        let config_data = match std::fs::read_to_string(config_file) {
-            Ok(data) => data,
-            Err(_) => include_str!("../config.toml").to_owned(),
+        let args = Args::parse(std::env::args().skip(1))?;
+
+        // 指定的配置文件必须存在, 否则使用可执行文件旁的配置文件或内置的配置
+        //
+        // A given configuration file must exist, otherwise the one next to the executable
+        // or the embedded one is used
+        let env_config = std::env::var(format!("{}CONFIG", ENV_PREFIX)).ok();
+        let (config_name, config_data) = match args.config.or(env_config) {
+            Some(path) => {
+                let data = std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read config file {}", path))?;
+                (path, data)
+            }
+            None => {
+                let config_file = exe_dir.join("config.toml");
+                match std::fs::read_to_string(&config_file) {
+                    Ok(data) => (config_file.display().to_string(), data),
+                    Err(_) => (
+                        "embedded config.toml".to_owned(),
+                        include_str!("../config.toml").to_owned(),
+                    ),
+                }
+            }
         };
-        let result: ConfigFile = basic_toml::from_str(&config_data).expect("load config file fail");
+        let mut map: Map<String, Value> = basic_toml::from_str(&config_data)
+            .with_context(|| format!("failed to parse config file {}", config_name))?;
+        let mut sources: HashMap<String, String> = map
+            .keys()
+            .map(|key| (key.to_owned(), config_name.to_owned()))
+            .collect();
+
+        for key in KEYS {
+            let name = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+            if let Ok(raw) = std::env::var(&name) {
+                map.insert(key.to_string(), coerce(key, raw));
+                sources.insert(key.to_string(), format!("environment variable {}", name));
+            }
+        }
+        for (key, raw) in args.overrides {
+            let value = coerce(&key, raw);
+            sources.insert(key.to_owned(), format!("argument --{}", key));
+            map.insert(key, value);
+        }
+
+        // 逐项检查类型, 以便在错误中指出配置项
+        //
+        // Check the types one by one so the error names the key
+        for (key, value) in map.iter() {
+            if !KEYS.contains(&key.as_str()) {
+                bail!("unknown key `{}` in {}", key, sources[key]);
+            }
+            if let Err(e) = check(key, value) {
+                bail!("invalid value for `{}` from {}: {}", key, sources[key], e);
+            }
+        }
+        let result: ConfigFile = serde_json::from_value(Value::Object(map))?;
+
         let server_name = result
             .server_name
             .unwrap_or(env!("CARGO_PKG_NAME").to_owned());
 
+        let host = match result.host {
+            Some(host) => host
+                .parse::<IpAddr>()
+                .map_err(|_| invalid("host", "expected an IPv4 or IPv6 address"))?,
+            None => IpAddr::from([0, 0, 0, 0]),
+        };
         let port = result.port.unwrap_or(8000);
-        let server_url = format!("0.0.0.0:{}", port);
+        let server_addr = SocketAddr::new(host, port);
+        let ipv6_only = result.ipv6_only.unwrap_or(false);
+        let unix_socket = match result.unix_socket {
+            Some(path) if path.is_empty() => {
+                return Err(invalid("unix_socket", "must not be empty"))
+            }
+            Some(path) if !cfg!(unix) => {
+                return Err(invalid(
+                    "unix_socket",
+                    &format!("{} is not supported on this platform", path),
+                ))
+            }
+            path => path.map(PathBuf::from),
+        };
+        let unix_socket_mode = match result.unix_socket_mode {
+            Some(mode) => u32::from_str_radix(&mode, 8)
+                .ok()
+                .filter(|mode| *mode <= 0o777)
+                .ok_or_else(|| {
+                    invalid("unix_socket_mode", "expected octal permissions like 660")
+                })?,
+            None => 0o660,
+        };
+        let listen_tcp = result.listen_tcp.unwrap_or(true);
+        if !listen_tcp && unix_socket.is_none() {
+            return Err(invalid("listen_tcp", "unix_socket is required when false"));
+        }
+        let tls13_only = match result.tls_min_version.as_deref() {
+            None | Some("1.2") => false,
+            Some("1.3") => true,
+            Some(_) => return Err(invalid("tls_min_version", "expected 1.2 or 1.3")),
+        };
+        let tls = match (result.tls_cert, result.tls_key) {
+            (Some(cert), Some(key)) => Some(TlsConfig {
+                cert: PathBuf::from(cert),
+                key: PathBuf::from(key),
+                tls13_only,
+            }),
+            (Some(_), None) => return Err(invalid("tls_key", "required with tls_cert")),
+            (None, Some(_)) => return Err(invalid("tls_cert", "required with tls_key")),
+            (None, None) => None,
+        };
 
         let log_level = result.log_level.unwrap_or("info".to_owned());
+        if !["trace", "debug", "info", "warn", "error"].contains(&log_level.as_str()) {
+            return Err(invalid(
+                "log_level",
+                "expected trace, debug, info, warn or error",
+            ));
+        }
         let log_split = result.log_split.unwrap_or("day".to_owned());
+        if !["day", "hour", "minute"].contains(&log_split.as_str()) {
+            return Err(invalid("log_split", "expected day, hour or minute"));
+        }
+        let log_dir = match result.log_dir {
+            Some(dir) if dir.is_empty() => return Err(invalid("log_dir", "must not be empty")),
+            Some(dir) => PathBuf::from(dir),
+            None => exe_dir.join("logs"),
+        };
+        let default_target = match cfg!(debug_assertions) {
+            true => "stdout",
+            false => "file",
+        };
+        let (log_stdout, log_file) = match result.log_target.as_deref().unwrap_or(default_target) {
+            "stdout" => (true, false),
+            "file" => (false, true),
+            "both" => (true, true),
+            _ => return Err(invalid("log_target", "expected stdout, file or both")),
+        };
+        let log_json = match result.log_format.as_deref() {
+            None | Some("text") => false,
+            Some("json") => true,
+            Some(_) => return Err(invalid("log_format", "expected text or json")),
+        };
+        let log_timezone = match result.log_timezone.as_deref() {
+            None => Some(UtcOffset::from_hms(8, 0, 0).unwrap()),
+            Some("local") => None,
+            Some(timezone) => Some(parse_offset(timezone).ok_or_else(|| {
+                invalid(
+                    "log_timezone",
+                    "expected local, UTC or an offset like +08:00",
+                )
+            })?),
+        };
+        let max_log_files = result.max_log_files.unwrap_or(0);
         let sync_interval = result.sync_interval.unwrap_or(30);
-        ApplicationConfig {
+        if sync_interval == 0 {
+            return Err(invalid("sync_interval", "must be at least 1"));
+        }
+        let data_dir = match result.data_dir {
+            Some(dir) if dir.is_empty() => return Err(invalid("data_dir", "must not be empty")),
+            Some(dir) => PathBuf::from(dir),
+            None => exe_dir.join("data"),
+        };
+        let admin_import = result.admin_import.unwrap_or(false);
+        let snapshot_dir = result
+            .snapshot_dir
+            .map(PathBuf::from)
+            .unwrap_or(data_dir.join("snapshots"));
+        let snapshot_interval = result.snapshot_interval.unwrap_or(0);
+        let snapshot_keep = result.snapshot_keep.unwrap_or(7).max(1);
+        let stream_tick = result.stream_tick.unwrap_or(1000).max(1);
+        let alert_interval = result.alert_interval.unwrap_or(10).max(1);
+        let alert_retries = result.alert_retries.unwrap_or(3);
+        if result
+            .alert_webhook
+            .as_deref()
+            .is_some_and(|webhook| !util::is_valid_url(webhook))
+        {
+            return Err(invalid("alert_webhook", "expected an http or https url"));
+        }
+        let label_limit = result.label_limit.unwrap_or(1000);
+        let limit_keys = result.limit_keys.unwrap_or(100_000);
+        let cors_origins = result.cors_origins.unwrap_or(vec!["*".to_owned()]);
+        let cors_admin_origins = result.cors_admin_origins.unwrap_or_default();
+        let cors_app_origins = result.cors_app_origins.unwrap_or_default();
+        let cors_credentials = result.cors_credentials.unwrap_or(false);
+        let cors_max_age = result.cors_max_age.unwrap_or(0);
+        let all_origins = [
+            ("cors_origins", &cors_origins),
+            ("cors_admin_origins", &cors_admin_origins),
+        ]
+        .into_iter()
+        .chain(
+            cors_app_origins
+                .values()
+                .map(|origins| ("cors_app_origins", origins)),
+        );
+        for (key, origins) in all_origins {
+            if origins
+                .iter()
+                .any(|origin| origin.is_empty() || origin.parse::<HeaderValue>().is_err())
+            {
+                return Err(invalid(key, "expected a list of origins or `*`"));
+            }
+            // 携带凭据时不允许所有来源
+            //
+            // All origins are not allowed with credentials
+            if cors_credentials && origins.iter().any(|origin| origin == "*") {
+                return Err(invalid(key, "`*` cannot be used with cors_credentials"));
+            }
+        }
+        let idempotency_ttl = result.idempotency_ttl.unwrap_or(86400).max(1);
+        let idempotency_capacity = result.idempotency_capacity.unwrap_or(10000);
+        let idempotency_persist = result.idempotency_persist.unwrap_or(false);
+        Ok(ApplicationConfig {
             server_name,
-            server_url,
+            server_addr,
+            ipv6_only,
+            listen_tcp,
+            unix_socket,
+            unix_socket_mode,
+            tls,
             log_level,
             log_split,
-            exe_dir: exe_dir.to_path_buf(),
+            log_dir,
+            log_stdout,
+            log_file,
+            log_json,
+            log_timezone,
+            max_log_files,
             sync_interval,
+            data_dir,
+            admin_import,
+            snapshot_dir,
+            snapshot_interval,
+            snapshot_keep,
+            stream_tick,
+            alert_interval,
+            alert_webhook: result.alert_webhook,
+            alert_retries,
+            label_limit,
+            limit_keys,
+            cors_origins,
+            cors_admin_origins,
+            cors_app_origins,
+            cors_credentials,
+            cors_max_age,
+            idempotency_ttl,
+            idempotency_capacity,
+            idempotency_persist,
+            args: args.rest,
+        })
+    }
+}
+
+impl ApplicationConfig {
+    /// 需要重启才能生效且与 `other` 不同的配置项
+    ///
+    /// Keys that differ from `other` and need a restart to take effect
+    fn restart_keys(&self, other: &Self) -> Vec<&'static str> {
+        let (tls, other_tls) = (self.tls.as_ref(), other.tls.as_ref());
+        [
+            ("server_name", self.server_name != other.server_name),
+            ("host", self.server_addr.ip() != other.server_addr.ip()),
+            ("port", self.server_addr.port() != other.server_addr.port()),
+            ("ipv6_only", self.ipv6_only != other.ipv6_only),
+            ("listen_tcp", self.listen_tcp != other.listen_tcp),
+            ("unix_socket", self.unix_socket != other.unix_socket),
+            (
+                "unix_socket_mode",
+                self.unix_socket_mode != other.unix_socket_mode,
+            ),
+            (
+                "tls_cert",
+                tls.map(|t| &t.cert) != other_tls.map(|t| &t.cert),
+            ),
+            ("tls_key", tls.map(|t| &t.key) != other_tls.map(|t| &t.key)),
+            (
+                "tls_min_version",
+                tls.map(|t| t.tls13_only) != other_tls.map(|t| t.tls13_only),
+            ),
+            ("log_split", self.log_split != other.log_split),
+            ("log_dir", self.log_dir != other.log_dir),
+            (
+                "log_target",
+                (self.log_stdout, self.log_file) != (other.log_stdout, other.log_file),
+            ),
+            ("log_format", self.log_json != other.log_json),
+            ("log_timezone", self.log_timezone != other.log_timezone),
+            ("max_log_files", self.max_log_files != other.max_log_files),
+            ("data_dir", self.data_dir != other.data_dir),
+            (
+                "idempotency_ttl",
+                self.idempotency_ttl != other.idempotency_ttl,
+            ),
+            (
+                "idempotency_capacity",
+                self.idempotency_capacity != other.idempotency_capacity,
+            ),
+            (
+                "idempotency_persist",
+                self.idempotency_persist != other.idempotency_persist,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, changed)| changed.then_some(key))
+        .collect()
+    }
+}
+
+/// 重新读取配置, 应用可以在运行时生效的配置项, 并返回需要重启才能生效的配置项
+///
+/// 配置无效时保持原来的配置
+///
+/// Load the configuration again, apply the keys that can take effect at runtime,
+/// and return the keys that need a restart to take effect
+///
+/// The old configuration is kept when the new one is invalid
+pub fn reload() -> Result<ReloadVO> {
+    let new = ApplicationConfig::load()?;
+    let mut current = CURRENT.write();
+    let mut next = (**current).clone();
+    let mut applied = vec![];
+    macro_rules! apply {
+        ($($key:ident),+) => {$(
+            if next.$key != new.$key {
+                next.$key = new.$key.clone();
+                applied.push(stringify!($key).to_owned());
+            }
+        )+};
+    }
+    apply!(
+        log_level,
+        sync_interval,
+        admin_import,
+        snapshot_dir,
+        snapshot_interval,
+        snapshot_keep,
+        stream_tick,
+        alert_interval,
+        alert_webhook,
+        alert_retries,
+        label_limit,
+        limit_keys,
+        cors_origins,
+        cors_admin_origins,
+        cors_app_origins,
+        cors_credentials,
+        cors_max_age
+    );
+    if next.log_level != current.log_level {
+        log::set_level(&next.log_level);
+    }
+    *current = Arc::new(next);
+
+    // 与启动时的配置比较, 未重启前每次重新加载都会报告
+    //
+    // Compared with the configuration at startup, so they are reported on every reload until a restart
+    let restart: Vec<String> = new
+        .restart_keys(&CONFIG)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    info!("Configuration reloaded, applied: {:?}", applied);
+    if !restart.is_empty() {
+        warn!("Configuration changes need a restart: {:?}", restart);
+    }
+    Ok(ReloadVO { applied, restart })
+}
+
+/// 收到 SIGHUP 时重新加载配置
+///
+/// Reload the configuration on SIGHUP
+#[cfg(unix)]
+pub async fn reload_task() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            error!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+    info!("Reload task started");
+    while hangup.recv().await.is_some() {
+        if let Err(e) = reload() {
+            error!("Failed to reload configuration: {:#}", e);
         }
     }
 }
@@ -0,0 +1,91 @@
+use axum::extract::Query;
+
+use crate::{
+    config::{self, CONFIG},
+    context,
+    error::{
+        CONFIG_IS_NO_VALID, IMPORT_DATA_IS_NO_VALID, IMPORT_DISABLED, IMPORT_MODE_IS_NO_VALID,
+        SNAPSHOT_FAILED,
+    },
+    import::{self, Mode},
+    model::{
+        dto::ImportQueryDTO,
+        vo::{
+            config::ReloadVO,
+            health::{PendingVO, StatusVO},
+            import::ImportVO,
+        },
+    },
+    resp::Resp,
+    snapshot, sync, util,
+};
+
+/// 导入请求体的最大字节数, 更大的文件可以通过命令行导入
+///
+/// Maximum size of an import request body, larger files can be imported from the command line
+pub const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// 导入数据, 需要开启 `admin_import`
+///
+/// Import data, `admin_import` must be enabled
+pub async fn import(Query(query): Query<ImportQueryDTO>, body: String) -> Resp<ImportVO> {
+    if !config::current().admin_import {
+        return Resp::fail(IMPORT_DISABLED);
+    }
+    let mode = match Mode::parse(query.mode.as_deref()) {
+        Some(mode) => mode,
+        None => return Resp::fail(IMPORT_MODE_IS_NO_VALID),
+    };
+    let rows = match import::parse(&body) {
+        Ok(rows) => rows,
+        Err(e) => return Resp::fail((IMPORT_DATA_IS_NO_VALID.0, &e.to_string())),
+    };
+    Resp::success(import::run(rows, mode).await)
+}
+
+/// 生成数据库快照
+///
+/// Make a database snapshot
+pub async fn snapshot() -> Resp<String> {
+    match snapshot::snapshot(None).await {
+        Ok(path) => Resp::success(path.display().to_string()),
+        Err(e) => Resp::fail((SNAPSHOT_FAILED.0, &e.to_string())),
+    }
+}
+
+/// 重新加载配置
+///
+/// Reload the configuration
+pub async fn reload() -> Resp<ReloadVO> {
+    match config::reload() {
+        Ok(vo) => Resp::success(vo),
+        Err(e) => Resp::fail((CONFIG_IS_NO_VALID.0, &format!("{:#}", e))),
+    }
+}
+
+/// 服务状态
+///
+/// Service status
+pub async fn status() -> Resp<StatusVO> {
+    let db_size = std::fs::metadata(CONFIG.data_dir.join("db.sqlite"))
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    let last_sync = sync::last_sync();
+    Resp::success(StatusVO {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        uptime: util::now() - context!().started,
+        apps: context!().apps.count(),
+        apis: context!().apis.count(),
+        pending: PendingVO {
+            apps: context!().wait_app.pending(),
+            apis: context!().wait_api.pending(),
+            records: context!().wait_record.pending(),
+            counts: context!().counts.pending(),
+            kv: context!().kv.pending(),
+            idempotency: context!().idempotency.pending(),
+        },
+        db_size,
+        last_sync: last_sync.map(|(time, _)| time),
+        last_sync_ms: last_sync.map(|(_, duration)| duration.as_secs_f64() * 1000.0),
+    })
+}
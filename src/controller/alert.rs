@@ -0,0 +1,116 @@
+use axum::extract::Path;
+use tracing::info;
+
+use crate::{
+    context,
+    db::{add_alert, delete_alert, update_alert},
+    error::{
+        ALERT_KIND_IS_NO_VALID, ALERT_NOT_FOUND, ALERT_WEBHOOK_IS_NO_VALID,
+        ALERT_WINDOW_IS_NO_VALID, API_NOT_FOUND, APP_NOT_FOUND,
+    },
+    handler::Json,
+    model::{dto::AlertDTO, Alert},
+    resp::Resp,
+    util,
+};
+
+/// 检查告警规则并转换为 Alert
+///
+/// Check the alert rule and convert it into an Alert
+fn check(id: i64, dto: AlertDTO) -> Result<Alert, (i64, &'static str)> {
+    if !context!().apps.check_app(&dto.app) {
+        return Err(APP_NOT_FOUND);
+    }
+    if let Some(api) = &dto.api {
+        if !context!().apis.check_api(&dto.app, api) {
+            return Err(API_NOT_FOUND);
+        }
+    }
+    let window = match &dto.kind[..] {
+        "above" | "idle" => match dto.window {
+            Some(window) if window > 0 && window <= 24 * 60 * 60 => window,
+            _ => return Err(ALERT_WINDOW_IS_NO_VALID),
+        },
+        "total" => 0,
+        _ => return Err(ALERT_KIND_IS_NO_VALID),
+    };
+    if dto
+        .webhook
+        .as_deref()
+        .is_some_and(|webhook| !util::is_valid_url(webhook))
+    {
+        return Err(ALERT_WEBHOOK_IS_NO_VALID);
+    }
+    Ok(Alert {
+        id,
+        app: dto.app,
+        api: dto.api,
+        kind: dto.kind,
+        threshold: dto.threshold.unwrap_or_default(),
+        window,
+        webhook: dto.webhook,
+        firing: false,
+    })
+}
+
+/// 新增告警规则
+///
+/// Add an alert rule
+pub async fn add(Json(dto): Json<AlertDTO>) -> Resp<Alert> {
+    let mut alert = match check(0, dto) {
+        Ok(alert) => alert,
+        Err(e) => return Resp::fail(e),
+    };
+    alert.id = add_alert(&alert).await;
+    info!("Add alert: {:?}", alert);
+    context!().alerts.set(alert.clone());
+    Resp::success(alert)
+}
+
+/// 获取所有告警规则
+///
+/// Get all alert rules
+pub async fn list() -> Resp<Vec<Alert>> {
+    Resp::success(context!().alerts.get_all())
+}
+
+/// 获取告警规则
+///
+/// Get an alert rule
+pub async fn get(Path(id): Path<i64>) -> Resp<Alert> {
+    match context!().alerts.get(id) {
+        Some(alert) => Resp::success(alert),
+        None => Resp::fail(ALERT_NOT_FOUND),
+    }
+}
+
+/// 修改告警规则, 状态会被重置
+///
+/// Update an alert rule, the state is reset
+pub async fn update(Path(id): Path<i64>, Json(dto): Json<AlertDTO>) -> Resp<Alert> {
+    if context!().alerts.get(id).is_none() {
+        return Resp::fail(ALERT_NOT_FOUND);
+    }
+    let alert = match check(id, dto) {
+        Ok(alert) => alert,
+        Err(e) => return Resp::fail(e),
+    };
+    update_alert(&alert).await;
+    info!("Update alert: {:?}", alert);
+    context!().alerts.set(alert.clone());
+    Resp::success(alert)
+}
+
+/// 删除告警规则
+///
+/// Delete an alert rule
+pub async fn delete(Path(id): Path<i64>) -> Resp<Alert> {
+    match context!().alerts.remove(id) {
+        Some(alert) => {
+            delete_alert(id).await;
+            info!("Delete alert: {}", id);
+            Resp::success(alert)
+        }
+        None => Resp::fail(ALERT_NOT_FOUND),
+    }
+}
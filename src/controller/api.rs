@@ -1,16 +1,43 @@
-use anyhow::Ok;
-use axum::extract::Path;
-use tracing::info;
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path},
+    http::{header::USER_AGENT, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use tracing::{info, warn};
 
 use crate::{
-    context,
-    error::{API_ALREADY_EXISTS, API_NAME_IS_NO_VALID, API_NOT_FOUND, APP_NOT_FOUND},
+    common::{
+        idempotency::{self, Begin},
+        label, visitor,
+        watch::Target,
+    },
+    config, context,
+    error::{
+        API_ALREADY_EXISTS, API_NAME_IS_NO_VALID, API_NOT_FOUND, APP_NOT_FOUND,
+        HISTOGRAM_VALUE_IS_NO_VALID, IDEMPOTENCY_KEY_API_MISMATCH, IDEMPOTENCY_KEY_IN_PROGRESS,
+        IDEMPOTENCY_KEY_IS_NO_VALID, LABEL_IS_NO_VALID, LABEL_LIMIT_EXCEEDED, QUOTA_EXCEEDED,
+    },
     handler::Json,
-    model::dto::AddApiDTO,
+    model::{
+        dto::{AddApiDTO, GetApiDTO, PostApiDTO},
+        vo::{api::GetApiVO, quota::QuotaVO},
+    },
     resp::Resp,
     util,
 };
 
+/// 幂等键请求头, 重复的请求返回原来的结果
+///
+/// Idempotency key header, a duplicate request returns the original result
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// 返回原来结果时附带的响应头
+///
+/// Response header sent with a replayed result
+const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
 /// 新增 Api
 ///
 /// Add Api
@@ -30,40 +57,232 @@ pub async fn add(
 
     info!("Add api: {} to app: {}", api, app);
 
-    context!().apis.add_api(&app, &api);
-    context!().wait_api.add_api(&app, &api);
+    create(&app, &api);
 
     Resp::success("Success".to_owned())
 }
 
+/// 创建 api, 调用前需确保 app 存在且 api 不存在
+///
+/// Create an api, the caller must ensure the app exists and the api does not
+pub fn create(app: &str, api: &str) {
+    context!().apis.add_api(app, api);
+    context!().wait_api.add_api(app, api);
+}
+
 /// 获取 api 访问数量
 ///
 /// Get api access count
-pub async fn get(Path((app, api)): Path<(String, String)>) -> Resp<i64> {
+pub async fn get(
+    Path((app, api)): Path<(String, String)>,
+    body: Option<Json<GetApiDTO>>,
+) -> Resp<GetApiVO> {
     if !context!().apps.check_app(&app) {
         return Resp::fail(APP_NOT_FOUND);
     };
     if !context!().apis.check_api(&app, &api) {
         return Resp::fail(API_NOT_FOUND);
     };
-    Ok(context!().apis.get_api(&app, &api)).into()
+    let count = context!().apis.get_api(&app, &api);
+    let dto = match body {
+        Some(Json(dto)) => dto,
+        None => return Resp::success(GetApiVO::Count(count)),
+    };
+    let windows = dto
+        .windows
+        .unwrap_or_default()
+        .then(|| context!().windows.get_api(&app, &api, util::now()));
+    let (count, groups) = match (dto.labels, dto.group) {
+        (None, None) if windows.is_none() => return Resp::success(GetApiVO::Count(count)),
+        (None, None) => (count, None),
+        (labels, group) => {
+            let (count, groups) = context!().labels.query(
+                &app,
+                &api,
+                &labels.unwrap_or_default(),
+                group.as_deref().unwrap_or_default(),
+            );
+            (count, group.map(|_| groups))
+        }
+    };
+    Resp::success(GetApiVO::Detail {
+        count,
+        windows,
+        groups,
+    })
 }
 
-/// 新增记录
+/// 新增记录, 同时记录访客, 标签与数值. 带有幂等键时, 重复的请求返回原来的调用次数
 ///
-/// Add record
-pub async fn post(Path((app, api)): Path<(String, String)>) -> Resp<i64> {
-    if !context!().apps.check_app(&app) {
-        return Resp::fail(APP_NOT_FOUND);
+/// Add record, and record the visitor, labels and value. With an idempotency key,
+/// a duplicate request returns the original count
+pub async fn post(
+    Path((app, api)): Path<(String, String)>,
+    addr: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Option<Json<PostApiDTO>>,
+) -> Response {
+    let (visitor, labels, value) = match body {
+        Some(Json(PostApiDTO {
+            visitor,
+            labels,
+            value,
+        })) => (visitor, labels, value),
+        None => (None, None, None),
     };
-    if !context!().apis.check_api(&app, &api) {
-        return Resp::fail(API_NOT_FOUND);
+    let idempotency_key = match headers.get(IDEMPOTENCY_KEY) {
+        Some(key) => match key.to_str().ok().filter(|key| idempotency::is_valid(key)) {
+            Some(key) => Some(key.to_owned()),
+            None => return Resp::<()>::fail(IDEMPOTENCY_KEY_IS_NO_VALID).into_response(),
+        },
+        None => None,
+    };
+    // `-0.0` 的符号位会使其落入最大的桶, 因此按符号位判断
+    //
+    // The sign bit of `-0.0` would put it in the largest bucket, so check the sign bit
+    if value.is_some_and(|value| !value.is_finite() || value.is_sign_negative()) {
+        return Resp::<()>::fail(HISTOGRAM_VALUE_IS_NO_VALID).into_response();
+    }
+    let labels = match labels.filter(|labels| !labels.is_empty()) {
+        Some(labels) => {
+            if !label::is_valid(&labels) {
+                return Resp::<()>::fail(LABEL_IS_NO_VALID).into_response();
+            }
+            let key = label::key(&labels);
+            if !context!()
+                .labels
+                .check(&app, &api, &key, config::current().label_limit)
+            {
+                return Resp::<()>::fail(LABEL_LIMIT_EXCEEDED).into_response();
+            }
+            Some((key, labels))
+        }
+        None => None,
+    };
+
+    if let Some(key) = &idempotency_key {
+        if !context!().apps.check_app(&app) {
+            return Resp::<()>::fail(APP_NOT_FOUND).into_response();
+        }
+        match context!().idempotency.begin(&app, &api, key, util::now()) {
+            Begin::New => {}
+            Begin::Done(count) => {
+                return ([(IDEMPOTENT_REPLAYED, "true")], Resp::success(count)).into_response()
+            }
+            Begin::InProgress => {
+                return Resp::<()>::fail(IDEMPOTENCY_KEY_IN_PROGRESS).into_response()
+            }
+            Begin::Mismatch => {
+                return Resp::<()>::fail(IDEMPOTENCY_KEY_API_MISMATCH).into_response()
+            }
+        }
+    }
+
+    let count = match hit(&app, &api) {
+        Ok(count) => count,
+        Err(e) => {
+            if let Some(key) = &idempotency_key {
+                context!().idempotency.abort(&app, key);
+            }
+            return e.into_response();
+        }
+    };
+    if let Some(key) = &idempotency_key {
+        context!()
+            .idempotency
+            .finish(&app, &api, key, count, util::now());
+    }
+
+    // 提前检查之后其他请求可能已经用完了组合数量, 此时只记录调用
+    //
+    // Other requests may have used up the combinations since the early check, only the call is recorded then
+    if let Some((key, labels)) = labels {
+        if !context!()
+            .labels
+            .add(&app, &api, key, labels, config::current().label_limit)
+        {
+            warn!("Label limit exceeded after the check: {} {}", app, api);
+        }
+    }
+
+    let now = util::now();
+    if let Some(value) = value {
+        context!().histograms.add(&app, &api, value, now);
+    }
+
+    let hash = match (visitor, addr) {
+        (Some(visitor), _) => Some(visitor::hash(visitor.as_bytes())),
+        (None, Some(ConnectInfo(addr))) => {
+            let ip = addr.ip().to_canonical().to_string();
+            let user_agent = headers
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            Some(context!().visitors.derive(&ip, user_agent, now))
+        }
+        // 通过 Unix 套接字的调用没有 ip, 所有调用方会被当作同一个访客, 因此不记录
+        //
+        // Calls through the Unix socket have no ip, all callers would count as one visitor,
+        // so it is not recorded
+        (None, None) => None,
+    };
+    if let Some(hash) = hash {
+        context!().visitors.add(&app, &api, hash, now);
+    }
+
+    Resp::success(count).into_response()
+}
+
+/// 记录调用失败的原因
+///
+/// Reason a call could not be recorded
+pub enum HitError {
+    Fail((i64, &'static str)),
+    /// 配额已用尽, 附带配额的用量与重置时间
+    ///
+    /// The quota is exhausted, with its usage and reset time
+    Quota(QuotaVO),
+}
+
+impl IntoResponse for HitError {
+    fn into_response(self) -> Response {
+        match self {
+            HitError::Fail(e) => Resp::<()>::fail(e).into_response(),
+            HitError::Quota(quota) => Resp::fail_with(QUOTA_EXCEEDED, quota).into_response(),
+        }
+    }
+}
+
+impl From<HitError> for (i64, &'static str) {
+    fn from(e: HitError) -> Self {
+        match e {
+            HitError::Fail(e) => e,
+            HitError::Quota(_) => QUOTA_EXCEEDED,
+        }
+    }
+}
+
+/// 记录一次 api 调用, 返回调用后的次数
+///
+/// Record a call to the api, returns the count after the call
+pub fn hit(app: &str, api: &str) -> Result<i64, HitError> {
+    if !context!().apps.check_app(app) {
+        return Err(HitError::Fail(APP_NOT_FOUND));
+    };
+    if !context!().apis.check_api(app, api) {
+        return Err(HitError::Fail(API_NOT_FOUND));
     };
 
-    let count = context!().apis.get_api(&app, &api) + 1;
+    let now = util::now();
+    context!()
+        .quotas
+        .consume(app, api, now)
+        .map_err(HitError::Quota)?;
 
-    context!().apis.update(&app, &api);
-    context!().wait_record.add(&app, &api);
+    let count = context!().apis.update(app, api) + 1;
+    context!().wait_record.add(app, api);
+    context!().windows.add(app, api, now, 1);
+    context!().watches.notify(Target::Api(app, api));
 
-    Resp::success(count)
+    Ok(count)
 }
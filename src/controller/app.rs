@@ -1,3 +1,5 @@
+use std::cmp::Reverse;
+
 use axum::extract::Path;
 use tracing::info;
 
@@ -7,7 +9,10 @@ use crate::{
     handler::Json,
     model::{
         dto::{AddAppDTO, GetAppDTO},
-        vo::app::{ApiCount, GetAppVO},
+        vo::{
+            app::{ApiCount, GetAppVO},
+            window::WindowVO,
+        },
     },
     resp::Resp,
     util,
@@ -24,23 +29,38 @@ pub async fn get(Path(app): Path<String>, body: Option<Json<GetAppDTO>>) -> Resp
         Some(Json(dto)) => {
             let apis = context!().apis.get_apis(&app);
             let total: i64 = apis.values().sum();
+            let now = util::now();
+            let windows = dto.windows.unwrap_or(false);
 
             // 将 apis 转换为 ApiCount 结构体, 以便排序
             //
             // Convert apis to ApiCount structure for sorting
             let mut apis: Vec<ApiCount> = apis
                 .into_iter()
-                .map(|(api, count)| ApiCount { api, count })
+                .map(|(api, count)| ApiCount {
+                    windows: windows.then(|| context!().windows.get_api(&app, &api, now)),
+                    api,
+                    count,
+                })
                 .collect();
 
             // 除非特别指定, 否则默认按从大到小顺序
             //
             // Unless specified, the default is in descending order
             match dto.sort {
-                Some(false) => apis.sort_by(|a, b| a.count.cmp(&b.count)),
-                _ => apis.sort_by(|a, b| b.count.cmp(&a.count)),
+                Some(false) => apis.sort_by_key(|a| a.count),
+                _ => apis.sort_by_key(|a| Reverse(a.count)),
             }
 
+            // app 的滑动窗口计数为所有 api 之和
+            //
+            // The sliding window counts of the app are the sum of all apis
+            let app_windows = windows.then(|| {
+                apis.iter()
+                    .filter_map(|a| a.windows)
+                    .fold(WindowVO::default(), |sum, w| sum + w)
+            });
+
             // 返回部分结果
             //
             // Return part of the result
@@ -57,11 +77,13 @@ pub async fn get(Path(app): Path<String>, body: Option<Json<GetAppDTO>>) -> Resp
 
             Resp::success(GetAppVO {
                 total,
+                windows: app_windows,
                 apis: Some(apis),
             })
         }
         None => Resp::success(GetAppVO {
             total: context!().apis.get_sum(&app),
+            windows: None,
             apis: None,
         }),
     }
@@ -80,14 +102,21 @@ pub async fn add(Json(AddAppDTO { app }): Json<AddAppDTO>) -> Resp<String> {
 
     info!("Add app: {}", app);
 
+    create(&app);
+
+    Resp::success("Add app success".to_owned())
+}
+
+/// 创建 app, 调用前需确保 app 不存在
+///
+/// Create an app, the caller must ensure the app does not exist
+pub fn create(app: &str) {
     // 将新增 app 添加到 apis 内存对象中优先提供计数功能,
     // 以保证 新增 api 时 app 存在
     //
     // Add the new app to the apis memory object to provide counting function first,
     // to ensure that the app exists when adding a new api
-    context!().apis.add_app(&app);
-    context!().apps.add(&app);
-    context!().wait_app.add(&app);
-
-    Resp::success("Add app success".to_owned())
+    context!().apis.add_app(app);
+    context!().apps.add(app);
+    context!().wait_app.add(app);
 }
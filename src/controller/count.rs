@@ -0,0 +1,48 @@
+use axum::extract::Path;
+
+use crate::{
+    common::watch::Target,
+    context,
+    error::{COUNT_KEY_IS_NO_VALID, COUNT_NOT_FOUND, COUNT_OVERFLOW},
+    handler::Json,
+    model::dto::CountDTO,
+    resp::Resp,
+    util,
+};
+
+/// 获取计数, 用于检测 key 是否存在
+///
+/// Get the counter, used to check whether the key exists
+pub async fn get(Path(key): Path<String>) -> Resp<i64> {
+    match context!().counts.get(&key) {
+        Some(count) => Resp::success(count),
+        None => Resp::fail(COUNT_NOT_FOUND),
+    }
+}
+
+/// 累加计数
+///
+/// Add to the counter
+pub async fn add(Path(key): Path<String>, Json(CountDTO { data }): Json<CountDTO>) -> Resp<i64> {
+    if !util::is_valid_key(&key) {
+        return Resp::fail(COUNT_KEY_IS_NO_VALID);
+    }
+    let count = match context!().counts.add(&key, data) {
+        Some(count) => count,
+        None => return Resp::fail(COUNT_OVERFLOW),
+    };
+    context!().watches.notify(Target::Count(&key));
+    Resp::success(count)
+}
+
+/// 设置计数
+///
+/// Set the counter
+pub async fn set(Path(key): Path<String>, Json(CountDTO { data }): Json<CountDTO>) -> Resp<i64> {
+    if !util::is_valid_key(&key) {
+        return Resp::fail(COUNT_KEY_IS_NO_VALID);
+    }
+    context!().counts.set(&key, data);
+    context!().watches.notify(Target::Count(&key));
+    Resp::success(data)
+}
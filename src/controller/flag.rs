@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use axum::extract::Path;
+use hashbrown::HashSet;
+use serde_json::Value;
+use tracing::info;
+
+use crate::{
+    common::{
+        flag::Rule,
+        kv::Kind,
+        label::{self, Labels},
+        watch::Target,
+    },
+    config, context,
+    controller::api as Api,
+    db::{delete_flag, set_flag},
+    error::{APP_NOT_FOUND, FLAG_IS_NO_VALID, FLAG_NOT_FOUND, FLAG_USER_IS_NO_VALID},
+    handler::Json,
+    model::{
+        dto::{FlagDTO, FlagEvalDTO},
+        vo::flag::{FlagEvalVO, FlagVO},
+        Flag,
+    },
+    resp::Resp,
+    util,
+};
+
+/// 名单的最大长度
+///
+/// Maximum length of a list
+const MAX_LIST_LEN: usize = 10000;
+
+/// 总开关, 即布尔值存储中同名的值
+///
+/// The master switch, the value of the same key in the bool store
+fn enabled(key: &str) -> bool {
+    context!()
+        .kv
+        .store(Kind::Bool)
+        .get(key, util::now())
+        .is_some_and(|vo| vo.data == Value::Bool(true))
+}
+
+fn to_vo(key: &str, rule: &Rule) -> FlagVO {
+    let sorted = |set: &HashSet<String>| {
+        let mut list: Vec<String> = set.iter().cloned().collect();
+        list.sort();
+        list
+    };
+    FlagVO {
+        key: key.to_owned(),
+        app: rule.app.to_owned(),
+        percentage: rule.percentage,
+        allow: sorted(&rule.allow),
+        deny: sorted(&rule.deny),
+        enabled: enabled(key),
+    }
+}
+
+/// 获取功能开关
+///
+/// Get a feature flag
+pub async fn get(Path(key): Path<String>) -> Resp<FlagVO> {
+    match context!().flags.get(&key) {
+        Some(rule) => Resp::success(to_vo(&key, &rule)),
+        None => Resp::fail(FLAG_NOT_FOUND),
+    }
+}
+
+/// 设置功能开关, 同时创建记录曝光次数的同名 api
+///
+/// Set a feature flag, and create the api of the same name that records the exposures
+pub async fn set(Path(key): Path<String>, Json(dto): Json<FlagDTO>) -> Resp<FlagVO> {
+    if !util::is_valid(&key)
+        || !(0.0..=100.0).contains(&dto.percentage)
+        || dto.allow.len() > MAX_LIST_LEN
+        || dto.deny.len() > MAX_LIST_LEN
+    {
+        return Resp::fail(FLAG_IS_NO_VALID);
+    }
+    if !context!().apps.check_app(&dto.app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    if let Some(current) = context!().flags.get(&key) {
+        if current.app != dto.app {
+            return Resp::fail(FLAG_IS_NO_VALID);
+        }
+    }
+    if !context!().apis.check_api(&dto.app, &key) {
+        info!("Add api: {} to app: {}", key, dto.app);
+        Api::create(&dto.app, &key);
+    }
+    if let Some(enabled) = dto.enabled {
+        let _ = context!().kv.store(Kind::Bool).set(
+            &key,
+            Value::Bool(enabled),
+            None,
+            None,
+            util::now(),
+        );
+        context!().watches.notify(Target::Kv(Kind::Bool, &key));
+    }
+
+    let rule = Rule {
+        app: dto.app,
+        percentage: dto.percentage,
+        allow: dto.allow,
+        deny: dto.deny,
+    };
+    let vo = to_vo(&key, &rule);
+    set_flag(&Flag {
+        key: key.to_owned(),
+        app: vo.app.to_owned(),
+        percentage: vo.percentage,
+        allow: serde_json::to_string(&vo.allow).unwrap(),
+        deny: serde_json::to_string(&vo.deny).unwrap(),
+    })
+    .await;
+    info!("Set flag: {:?}", vo);
+    context!().flags.set(&key, rule);
+
+    Resp::success(vo)
+}
+
+/// 删除功能开关的规则, 总开关与曝光记录会被保留
+///
+/// Delete the rules of a feature flag, the master switch and the exposure records are kept
+pub async fn delete(Path(key): Path<String>) -> Resp<String> {
+    if !context!().flags.remove(&key) {
+        return Resp::fail(FLAG_NOT_FOUND);
+    }
+    delete_flag(&key).await;
+    info!("Delete flag: {}", key);
+    Resp::success("Success".to_owned())
+}
+
+/// 对用户求值, 每次求值都会记为同名 api 的一次调用, 并以 variant 标签区分结果
+///
+/// Evaluate for a user, every evaluation is recorded as a call to the api of the same name
+/// with a variant label for the result
+pub async fn evaluate(
+    Path(key): Path<String>,
+    Json(FlagEvalDTO { user }): Json<FlagEvalDTO>,
+) -> Resp<FlagEvalVO> {
+    if user.is_empty() || user.len() > 128 {
+        return Resp::fail(FLAG_USER_IS_NO_VALID);
+    }
+    let rule = match context!().flags.get(&key) {
+        Some(rule) => rule,
+        None => return Resp::fail(FLAG_NOT_FOUND),
+    };
+    let enabled = enabled(&key) && rule.evaluate(&key, &user);
+
+    // 曝光计数失败 (如配额用尽) 不影响求值
+    //
+    // A failed exposure count (such as an exhausted quota) does not affect the evaluation
+    if Api::hit(&rule.app, &key).is_ok() {
+        let labels: Labels = BTreeMap::from([(
+            "variant".to_owned(),
+            if enabled { "on" } else { "off" }.to_owned(),
+        )]);
+        let label_key = label::key(&labels);
+        context!().labels.add(
+            &rule.app,
+            &key,
+            label_key,
+            labels,
+            config::current().label_limit,
+        );
+    }
+
+    Resp::success(FlagEvalVO { key, user, enabled })
+}
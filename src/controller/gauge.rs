@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use axum::extract::{Path, Query};
+use tracing::info;
+
+use crate::{
+    common::gauge::{minute, Bucket},
+    context,
+    db::get_gauge_samples,
+    error::{
+        API_NOT_FOUND, APP_NOT_FOUND, GAUGE_NOT_FOUND, GAUGE_RANGE_IS_NO_VALID,
+        GAUGE_VALUE_IS_NO_VALID,
+    },
+    handler::Json,
+    model::{
+        dto::{GaugeDTO, GaugeQueryDTO, GaugeStepDTO},
+        vo::gauge::{GaugeBucketVO, GaugeVO},
+    },
+    resp::Resp,
+    util,
+};
+
+/// 一次查询最多的分钟数
+///
+/// Maximum number of minutes in a query
+const MAX_MINUTES: i64 = 7 * 24 * 60;
+
+/// 获取仪表的当前值与汇总
+///
+/// Get the current value and rollup of the gauge
+pub async fn get(
+    Path((app, api)): Path<(String, String)>,
+    Query(dto): Query<GaugeQueryDTO>,
+) -> Resp<GaugeVO> {
+    let value = match context!().gauges.get(&app, &api) {
+        Some(value) => value,
+        None => return Resp::fail(GAUGE_NOT_FOUND),
+    };
+    let now = util::now();
+    let to = minute(dto.to.unwrap_or(now));
+    let from = minute(dto.from.unwrap_or(to - 59 * 60));
+    if from > to || (to - from) / 60 >= MAX_MINUTES {
+        return Resp::fail(GAUGE_RANGE_IS_NO_VALID);
+    }
+
+    // 数据库中的采样早于等待写入的采样
+    //
+    // Samples in the database are earlier than the pending ones
+    let mut buckets: BTreeMap<i64, Bucket> = get_gauge_samples(&app, &api, from, to)
+        .await
+        .into_iter()
+        .map(|row| {
+            let bucket = Bucket {
+                min: row.min,
+                max: row.max,
+                sum: row.sum,
+                count: row.count,
+                last: row.last,
+            };
+            (row.time, bucket)
+        })
+        .collect();
+    for (time, bucket) in context!().gauges.get_pending(&app, &api, from, to) {
+        buckets
+            .entry(time)
+            .and_modify(|b| b.merge(&bucket))
+            .or_insert(bucket);
+    }
+
+    let total = buckets.values().copied().reduce(|mut a, b| {
+        a.merge(&b);
+        a
+    });
+    Resp::success(GaugeVO {
+        value,
+        from,
+        to,
+        min: total.map(|total| total.min),
+        max: total.map(|total| total.max),
+        avg: total.map(|total| total.sum / total.count as f64),
+        history: buckets
+            .into_iter()
+            .map(|(time, bucket)| GaugeBucketVO {
+                time,
+                min: bucket.min,
+                max: bucket.max,
+                avg: bucket.sum / bucket.count as f64,
+                last: bucket.last,
+            })
+            .collect(),
+    })
+}
+
+/// 设置仪表的值
+///
+/// Set the value of the gauge
+pub async fn set(
+    Path((app, api)): Path<(String, String)>,
+    Json(GaugeDTO { value }): Json<GaugeDTO>,
+) -> Resp<f64> {
+    update(&app, &api, value, |_| value)
+}
+
+/// 增加仪表的值, 默认为 1
+///
+/// Increase the value of the gauge, by 1 by default
+pub async fn inc(
+    Path((app, api)): Path<(String, String)>,
+    body: Option<Json<GaugeStepDTO>>,
+) -> Resp<f64> {
+    let by = body.and_then(|Json(dto)| dto.by).unwrap_or(1.0);
+    update(&app, &api, by, |value| value + by)
+}
+
+/// 减少仪表的值, 默认为 1
+///
+/// Decrease the value of the gauge, by 1 by default
+pub async fn dec(
+    Path((app, api)): Path<(String, String)>,
+    body: Option<Json<GaugeStepDTO>>,
+) -> Resp<f64> {
+    let by = body.and_then(|Json(dto)| dto.by).unwrap_or(1.0);
+    update(&app, &api, by, |value| value - by)
+}
+
+fn update(app: &str, api: &str, input: f64, f: impl FnOnce(f64) -> f64) -> Resp<f64> {
+    if !context!().apps.check_app(app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    if !context!().apis.check_api(app, api) {
+        return Resp::fail(API_NOT_FOUND);
+    }
+    if !input.is_finite() {
+        return Resp::fail(GAUGE_VALUE_IS_NO_VALID);
+    }
+    if context!().gauges.get(app, api).is_none() {
+        info!("Add gauge: {} {}", app, api);
+    }
+    // 增减的结果可能溢出为无穷大
+    //
+    // The result of an increase or decrease may overflow to infinity
+    match context!().gauges.update(app, api, util::now(), f) {
+        Some(value) => Resp::success(value),
+        None => Resp::fail(GAUGE_VALUE_IS_NO_VALID),
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    config, context, error::SERVICE_NOT_READY, model::vo::health::ReadyVO, pool, resp::Resp, sync,
+    util,
+};
+
+/// 数据库检查的超时时间
+///
+/// Timeout of the database check
+const DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 存活检查
+///
+/// Liveness check
+pub async fn healthz() -> Resp<String> {
+    Resp::success("ok".to_owned())
+}
+
+/// 就绪检查, 数据库可以访问且最近同步过时返回 200, 否则返回 503
+///
+/// 启动后尚未同步时以启动时间计算, 超过三个同步间隔未完成同步视为未就绪
+///
+/// Readiness check, returns 200 when the database is reachable and a sync completed recently,
+/// 503 otherwise
+///
+/// Before the first sync the startup time is used, no completed sync within three sync intervals
+/// is not ready
+pub async fn readyz() -> Response {
+    // 读取表结构, 确认数据库文件可以访问
+    //
+    // Read the schema to make sure the database file is accessible
+    let query = sqlx::query("select count(*) from sqlite_master");
+    let database = tokio::time::timeout(DATABASE_TIMEOUT, query.execute(pool!()))
+        .await
+        .is_ok_and(|result| result.is_ok());
+    let last_sync = sync::last_sync().map(|(time, _)| time);
+    let since = last_sync.unwrap_or(context!().started);
+    let synced = util::now() - since <= 3 * config::current().sync_interval as i64;
+
+    let vo = ReadyVO {
+        database,
+        synced,
+        last_sync,
+    };
+    match database && synced {
+        true => Resp::success(vo).into_response(),
+        false => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Resp::fail_with(SERVICE_NOT_READY, vo),
+        )
+            .into_response(),
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use axum::extract::{Path, Query};
+
+use crate::{
+    common::histogram::{hour, Histogram},
+    context,
+    db::get_histograms,
+    error::{API_NOT_FOUND, APP_NOT_FOUND, HISTOGRAM_RANGE_IS_NO_VALID},
+    model::{dto::HistogramDTO, vo::histogram::HistogramVO},
+    resp::Resp,
+    util,
+};
+
+/// 一次查询最多合并的小时数
+///
+/// Maximum number of hours merged in a query
+const MAX_HOURS: i64 = 31 * 24;
+
+/// 获取 api 的数值分布
+///
+/// Get the distribution of values of the api
+pub async fn get(
+    Path((app, api)): Path<(String, String)>,
+    Query(dto): Query<HistogramDTO>,
+) -> Resp<HistogramVO> {
+    if !context!().apps.check_app(&app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    if !context!().apis.check_api(&app, &api) {
+        return Resp::fail(API_NOT_FOUND);
+    }
+    let to = hour(dto.to.unwrap_or_else(util::now));
+    let from = dto.from.map(hour).unwrap_or(to);
+    if from > to || (to - from) / (60 * 60) >= MAX_HOURS {
+        return Resp::fail(HISTOGRAM_RANGE_IS_NO_VALID);
+    }
+
+    // 同一小时内存中的直方图包含数据库中的部分, 因此覆盖数据库中的
+    //
+    // A histogram in memory contains the database one of the same hour, so it replaces it
+    let mut hours: BTreeMap<i64, Histogram> = get_histograms(&app, &api, from, to)
+        .await
+        .into_iter()
+        .filter_map(|row| {
+            serde_json::from_str(&row.data)
+                .ok()
+                .map(|histogram| (row.time, histogram))
+        })
+        .collect();
+    hours.extend(context!().histograms.get_range(&app, &api, from, to));
+
+    let mut histogram = Histogram::default();
+    hours.values().for_each(|other| histogram.merge(other));
+
+    Resp::success(histogram.to_vo(from, to))
+}
@@ -0,0 +1,98 @@
+use axum::extract::Path;
+use serde_json::Value;
+
+use crate::{
+    common::{
+        kv::{Kind, MAX_TTL},
+        watch::Target,
+    },
+    context,
+    error::{KV_KEY_IS_NO_VALID, KV_NOT_FOUND, KV_VALUE_IS_NO_VALID, KV_VERSION_CONFLICT},
+    handler::Json,
+    model::{dto::KvDTO, vo::kv::KvVO},
+    resp::Resp,
+    util,
+};
+
+pub async fn get_bool(Path(key): Path<String>) -> Resp<KvVO> {
+    get(Kind::Bool, &key)
+}
+
+pub async fn set_bool(Path(key): Path<String>, Json(dto): Json<KvDTO>) -> Resp<KvVO> {
+    set(Kind::Bool, &key, dto)
+}
+
+pub async fn delete_bool(Path(key): Path<String>) -> Resp<String> {
+    delete(Kind::Bool, &key)
+}
+
+pub async fn get_string(Path(key): Path<String>) -> Resp<KvVO> {
+    get(Kind::String, &key)
+}
+
+pub async fn set_string(Path(key): Path<String>, Json(dto): Json<KvDTO>) -> Resp<KvVO> {
+    set(Kind::String, &key, dto)
+}
+
+pub async fn delete_string(Path(key): Path<String>) -> Resp<String> {
+    delete(Kind::String, &key)
+}
+
+pub async fn get_json(Path(key): Path<String>) -> Resp<KvVO> {
+    get(Kind::Json, &key)
+}
+
+pub async fn set_json(Path(key): Path<String>, Json(dto): Json<KvDTO>) -> Resp<KvVO> {
+    set(Kind::Json, &key, dto)
+}
+
+pub async fn delete_json(Path(key): Path<String>) -> Resp<String> {
+    delete(Kind::Json, &key)
+}
+
+/// 获取值, 用于检测 key 是否存在
+///
+/// Get the value, used to check whether the key exists
+fn get(kind: Kind, key: &str) -> Resp<KvVO> {
+    match context!().kv.store(kind).get(key, util::now()) {
+        Some(vo) => Resp::success(vo),
+        None => Resp::fail(KV_NOT_FOUND),
+    }
+}
+
+/// 设置值, 指定版本时为比较并设置
+///
+/// Set the value, compare-and-set when a version is given
+fn set(kind: Kind, key: &str, dto: KvDTO) -> Resp<KvVO> {
+    if !util::is_valid_key(key) {
+        return Resp::fail(KV_KEY_IS_NO_VALID);
+    }
+    if !kind.check(&dto.data)
+        || dto.data == Value::Null
+        || dto.ttl.is_some_and(|ttl| ttl <= 0 || ttl > MAX_TTL)
+    {
+        return Resp::fail(KV_VALUE_IS_NO_VALID);
+    }
+    match context!()
+        .kv
+        .store(kind)
+        .set(key, dto.data, dto.ttl, dto.version, util::now())
+    {
+        Ok(vo) => {
+            context!().watches.notify(Target::Kv(kind, key));
+            Resp::success(vo)
+        }
+        Err(Some(current)) => Resp::fail_with(KV_VERSION_CONFLICT, current),
+        Err(None) => Resp::fail(KV_VERSION_CONFLICT),
+    }
+}
+
+fn delete(kind: Kind, key: &str) -> Resp<String> {
+    match context!().kv.store(kind).delete(key, util::now()) {
+        true => {
+            context!().watches.notify(Target::Kv(kind, key));
+            Resp::success("Success".to_owned())
+        }
+        false => Resp::fail(KV_NOT_FOUND),
+    }
+}
@@ -0,0 +1,90 @@
+use axum::extract::Path;
+use tracing::info;
+
+use crate::{
+    common::limit::{Algorithm, Limiter},
+    config, context,
+    db::{delete_limit, set_limit},
+    error::{
+        API_NOT_FOUND, APP_NOT_FOUND, LIMIT_IS_NO_VALID, LIMIT_KEYS_EXCEEDED,
+        LIMIT_KEY_IS_NO_VALID, LIMIT_NOT_FOUND,
+    },
+    handler::Json,
+    model::{dto::LimitDTO, vo::limit::LimitVO, Limit},
+    resp::Resp,
+    util,
+};
+
+/// 获取 api 的限流规则
+///
+/// Get the rate limit rule of the api
+pub async fn get(Path((app, api)): Path<(String, String)>) -> Resp<Limit> {
+    match context!().limits.get(&app, &api) {
+        Some(limiter) => Resp::success(limiter.limit.clone()),
+        None => Resp::fail(LIMIT_NOT_FOUND),
+    }
+}
+
+/// 设置 api 的限流规则
+///
+/// Set the rate limit rule of the api
+pub async fn set(
+    Path((app, api)): Path<(String, String)>,
+    Json(dto): Json<LimitDTO>,
+) -> Resp<Limit> {
+    if !context!().apps.check_app(&app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    if !context!().apis.check_api(&app, &api) {
+        return Resp::fail(API_NOT_FOUND);
+    }
+    let limit = Limit {
+        app,
+        api,
+        algorithm: dto.algorithm,
+        capacity: dto.capacity,
+        refill: dto.refill.unwrap_or_default(),
+        window: dto.window.unwrap_or_default(),
+    };
+    let algorithm = match Algorithm::from_limit(&limit) {
+        Some(algorithm) => algorithm,
+        None => return Resp::fail(LIMIT_IS_NO_VALID),
+    };
+
+    set_limit(&limit).await;
+    info!("Set limit: {:?}", limit);
+    context!()
+        .limits
+        .set(Limiter::new(limit.clone(), algorithm));
+
+    Resp::success(limit)
+}
+
+/// 删除 api 的限流规则
+///
+/// Delete the rate limit rule of the api
+pub async fn delete(Path((app, api)): Path<(String, String)>) -> Resp<String> {
+    if !context!().limits.remove(&app, &api) {
+        return Resp::fail(LIMIT_NOT_FOUND);
+    }
+    delete_limit(&app, &api).await;
+    info!("Delete limit: {} {}", app, api);
+    Resp::success("Success".to_owned())
+}
+
+/// 检查并消耗 key 的限流额度
+///
+/// Check and consume the rate limit of the key
+pub async fn check(Path((app, api, key)): Path<(String, String, String)>) -> Resp<LimitVO> {
+    if key.is_empty() || key.len() > 128 {
+        return Resp::fail(LIMIT_KEY_IS_NO_VALID);
+    }
+    let limiter = match context!().limits.get(&app, &api) {
+        Some(limiter) => limiter,
+        None => return Resp::fail(LIMIT_NOT_FOUND),
+    };
+    match limiter.check(&key, util::now_f64(), config::current().limit_keys) {
+        Some(vo) => Resp::success(vo),
+        None => Resp::fail(LIMIT_KEYS_EXCEEDED),
+    }
+}
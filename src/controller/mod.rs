@@ -1,2 +1,16 @@
+pub mod admin;
+pub mod alert;
 pub mod api;
 pub mod app;
+pub mod count;
+pub mod flag;
+pub mod gauge;
+pub mod health;
+pub mod histogram;
+pub mod kv;
+pub mod limit;
+pub mod quota;
+pub mod stream;
+pub mod visitor;
+pub mod watch;
+pub mod ws;
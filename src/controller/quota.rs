@@ -0,0 +1,137 @@
+use axum::extract::Path;
+use tracing::info;
+
+use crate::{
+    common::quota::{Period, Usage},
+    context,
+    db::{delete_quota, set_quota, sum_rec},
+    error::{
+        API_NOT_FOUND, APP_NOT_FOUND, QUOTA_LIMIT_IS_NO_VALID, QUOTA_NOT_FOUND,
+        QUOTA_PERIOD_IS_NO_VALID,
+    },
+    handler::Json,
+    model::{dto::QuotaDTO, vo::quota::QuotaVO, Quota},
+    pool,
+    resp::Resp,
+    sync, util,
+};
+
+/// 获取所有配额及其用量
+///
+/// Get all quotas and their usage
+pub async fn list() -> Resp<Vec<QuotaVO>> {
+    Resp::success(context!().quotas.get_all(util::now()))
+}
+
+/// 获取 app 的配额
+///
+/// Get the quota of the app
+pub async fn get_app(Path(app): Path<String>) -> Resp<QuotaVO> {
+    get(&app, "")
+}
+
+/// 获取 api 的配额
+///
+/// Get the quota of the api
+pub async fn get_api(Path((app, api)): Path<(String, String)>) -> Resp<QuotaVO> {
+    get(&app, &api)
+}
+
+fn get(app: &str, api: &str) -> Resp<QuotaVO> {
+    match context!().quotas.get(app, api, util::now()) {
+        Some(quota) => Resp::success(quota),
+        None => Resp::fail(QUOTA_NOT_FOUND),
+    }
+}
+
+/// 设置 app 的配额
+///
+/// Set the quota of the app
+pub async fn set_app(Path(app): Path<String>, Json(dto): Json<QuotaDTO>) -> Resp<QuotaVO> {
+    set(app, String::new(), dto).await
+}
+
+/// 设置 api 的配额
+///
+/// Set the quota of the api
+pub async fn set_api(
+    Path((app, api)): Path<(String, String)>,
+    Json(dto): Json<QuotaDTO>,
+) -> Resp<QuotaVO> {
+    set(app, api, dto).await
+}
+
+/// 设置配额, 用量从当前周期已有的记录中统计
+///
+/// Set a quota, the usage is counted from the records of the current period
+async fn set(app: String, api: String, dto: QuotaDTO) -> Resp<QuotaVO> {
+    if !context!().apps.check_app(&app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    if !api.is_empty() && !context!().apis.check_api(&app, &api) {
+        return Resp::fail(API_NOT_FOUND);
+    }
+    if dto.limit < 0 {
+        return Resp::fail(QUOTA_LIMIT_IS_NO_VALID);
+    }
+    let period = match Period::parse(&dto.period) {
+        Some(period) => period,
+        None => return Resp::fail(QUOTA_PERIOD_IS_NO_VALID),
+    };
+
+    let now = util::now();
+    let start = period.bounds(now).0;
+    let names: Vec<String> = match api.is_empty() {
+        true => context!().apis.get_apis(&app).into_keys().collect(),
+        false => vec![api.to_owned()],
+    };
+    let api_filter = (!api.is_empty()).then_some(api.as_str());
+    // 持有同步锁, 以免同步在两次读取之间把记录移入数据库, 导致重复计算或遗漏
+    //
+    // Hold the sync lock so a sync cannot move records into the database between the two reads,
+    // which would count them twice or not at all
+    let used = {
+        let _lock = sync::lock().await;
+        sum_rec(pool!(), &app, &names, start).await
+            + context!().wait_record.sum_since(&app, api_filter, start)
+    };
+
+    let quota = Quota {
+        app,
+        api,
+        limit: dto.limit,
+        period: period.as_str().to_owned(),
+    };
+    set_quota(&quota).await;
+    info!("Set quota: {:?}", quota);
+
+    context!().quotas.set(
+        &quota.app,
+        &quota.api,
+        Usage::new(quota.limit, period, now, used),
+    );
+    get(&quota.app, &quota.api)
+}
+
+/// 删除 app 的配额
+///
+/// Delete the quota of the app
+pub async fn delete_app(Path(app): Path<String>) -> Resp<String> {
+    delete(&app, "").await
+}
+
+/// 删除 api 的配额
+///
+/// Delete the quota of the api
+pub async fn delete_api(Path((app, api)): Path<(String, String)>) -> Resp<String> {
+    delete(&app, &api).await
+}
+
+async fn delete(app: &str, api: &str) -> Resp<String> {
+    if !context!().quotas.remove(app, api) {
+        return Resp::fail(QUOTA_NOT_FOUND);
+    }
+    delete_quota(app, api).await;
+    info!("Delete quota: {} {}", app, api);
+    Resp::success("Success".to_owned())
+}
@@ -0,0 +1,48 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Path, Query},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+
+use crate::{
+    common::hub::StreamEvent, context, error::APP_NOT_FOUND, model::dto::StreamDTO, resp::Resp,
+    stream::make_vo,
+};
+
+/// 通过 SSE 推送 app 的实时访问量
+///
+/// Push the live access count of the app through SSE
+pub async fn stream(Path(app): Path<String>, Query(dto): Query<StreamDTO>) -> Response {
+    if !context!().apps.check_app(&app) {
+        return Resp::<()>::fail(APP_NOT_FOUND).into_response();
+    }
+    let rates = dto.rates.unwrap_or(false);
+    let select = move |event: &StreamEvent| match rates {
+        true => event.rates.clone(),
+        false => event.plain.clone(),
+    };
+
+    // 先订阅再生成完整数据, 避免错过两者之间的变化
+    //
+    // Subscribe before making the full data to avoid missing changes in between
+    let receiver = context!()
+        .hub
+        .subscribe(&app, || context!().apis.get_apis(&app));
+    let first = select(&StreamEvent::new(make_vo(&app, None).0));
+
+    let stream = tokio_stream::once(first)
+        .chain(
+            BroadcastStream::new(receiver)
+                .filter_map(move |event| event.ok().map(|event| select(&event))),
+        )
+        .map(|data| Ok::<_, Infallible>(Event::default().data(data)));
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
@@ -0,0 +1,70 @@
+use axum::extract::{Path, Query};
+
+use crate::{
+    common::visitor::{day, Sketch},
+    context,
+    db::get_visitors,
+    error::{API_NOT_FOUND, APP_NOT_FOUND, VISITOR_RANGE_IS_NO_VALID},
+    model::{dto::VisitorDTO, vo::visitor::VisitorVO},
+    resp::Resp,
+    util,
+};
+
+/// 一次查询最多合并的天数
+///
+/// Maximum number of days merged in a query
+const MAX_DAYS: i64 = 366;
+
+/// 获取 app 的不同访客数量
+///
+/// Get the number of distinct visitors of the app
+pub async fn get_app(Path(app): Path<String>, Query(dto): Query<VisitorDTO>) -> Resp<VisitorVO> {
+    if !context!().apps.check_app(&app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    get(&app, None, dto).await
+}
+
+/// 获取 api 的不同访客数量
+///
+/// Get the number of distinct visitors of the api
+pub async fn get_api(
+    Path((app, api)): Path<(String, String)>,
+    Query(dto): Query<VisitorDTO>,
+) -> Resp<VisitorVO> {
+    if !context!().apps.check_app(&app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    if !context!().apis.check_api(&app, &api) {
+        return Resp::fail(API_NOT_FOUND);
+    }
+    get(&app, Some(&api), dto).await
+}
+
+/// 合并数据库与内存中的草图, 内存中的草图包含了已写入的部分, 重复合并不影响结果
+///
+/// Merge the sketches in the database and in memory, the sketches in memory contain
+/// what was written so merging both does not change the result
+async fn get(app: &str, api: Option<&str>, dto: VisitorDTO) -> Resp<VisitorVO> {
+    let to = day(dto.to.unwrap_or_else(util::now));
+    let from = dto.from.map(day).unwrap_or(to);
+    if from > to || (to - from) / (24 * 60 * 60) >= MAX_DAYS {
+        return Resp::fail(VISITOR_RANGE_IS_NO_VALID);
+    }
+
+    let mut sketch = Sketch::default();
+    for visitor in get_visitors(app, api, from, to).await {
+        if let Some(other) = Sketch::from_bytes(visitor.sketch) {
+            sketch.merge(&other);
+        }
+    }
+    context!()
+        .visitors
+        .merge_into(&mut sketch, app, api, from, to);
+
+    Resp::success(VisitorVO {
+        from,
+        to,
+        visitors: sketch.count(),
+    })
+}
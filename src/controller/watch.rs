@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use axum::extract::{Path, Query};
+
+use crate::{
+    common::{kv::Kind, watch::Target},
+    context,
+    error::{API_NOT_FOUND, APP_NOT_FOUND, COUNT_KEY_IS_NO_VALID, KV_KEY_IS_NO_VALID},
+    model::{
+        dto::WatchDTO,
+        vo::{kv::KvVO, watch::WatchVO},
+    },
+    resp::Resp,
+    util,
+};
+
+/// 默认等待的秒数
+///
+/// Default seconds to wait
+const DEFAULT_TIMEOUT: u64 = 30;
+
+/// 最长等待的秒数
+///
+/// Maximum seconds to wait
+const MAX_TIMEOUT: u64 = 120;
+
+/// 等待 api 的调用次数改动
+///
+/// Wait for the number of calls of the api to change
+pub async fn api(
+    Path((app, api)): Path<(String, String)>,
+    Query(dto): Query<WatchDTO>,
+) -> Resp<WatchVO<i64>> {
+    if !context!().apps.check_app(&app) {
+        return Resp::fail(APP_NOT_FOUND);
+    }
+    if !context!().apis.check_api(&app, &api) {
+        return Resp::fail(API_NOT_FOUND);
+    }
+    let (version, changed) = wait(Target::Api(&app, &api), dto).await;
+    Resp::success(WatchVO {
+        version,
+        changed,
+        data: Some(context!().apis.get_api(&app, &api)),
+    })
+}
+
+/// 等待计数改动
+///
+/// Wait for the counter to change
+pub async fn count(Path(key): Path<String>, Query(dto): Query<WatchDTO>) -> Resp<WatchVO<i64>> {
+    if !util::is_valid_key(&key) {
+        return Resp::fail(COUNT_KEY_IS_NO_VALID);
+    }
+    let (version, changed) = wait(Target::Count(&key), dto).await;
+    Resp::success(WatchVO {
+        version,
+        changed,
+        data: context!().counts.get(&key),
+    })
+}
+
+pub async fn bool(Path(key): Path<String>, Query(dto): Query<WatchDTO>) -> Resp<WatchVO<KvVO>> {
+    kv(Kind::Bool, &key, dto).await
+}
+
+pub async fn string(Path(key): Path<String>, Query(dto): Query<WatchDTO>) -> Resp<WatchVO<KvVO>> {
+    kv(Kind::String, &key, dto).await
+}
+
+pub async fn json(Path(key): Path<String>, Query(dto): Query<WatchDTO>) -> Resp<WatchVO<KvVO>> {
+    kv(Kind::Json, &key, dto).await
+}
+
+/// 等待键值存储中的值改动, 包括删除与过期
+///
+/// Wait for the value in the key-value store to change, including deletion and expiry
+async fn kv(kind: Kind, key: &str, dto: WatchDTO) -> Resp<WatchVO<KvVO>> {
+    if !util::is_valid_key(key) {
+        return Resp::fail(KV_KEY_IS_NO_VALID);
+    }
+    let (version, changed) = wait(Target::Kv(kind, key), dto).await;
+    Resp::success(WatchVO {
+        version,
+        changed,
+        data: context!().kv.store(kind).get(key, util::now()),
+    })
+}
+
+/// 等待版本号与 `dto.version` 不同或超时, 返回当前的版本号以及是否改动
+///
+/// Wait until the version differs from `dto.version` or the timeout,
+/// returns the current version and whether it changed
+async fn wait(target: Target<'_>, dto: WatchDTO) -> (u64, bool) {
+    let mut receiver = context!().watches.subscribe(target);
+    let version = match dto.version {
+        Some(version) => version,
+        None => return (*receiver.borrow(), true),
+    };
+    let timeout = dto.timeout.unwrap_or(DEFAULT_TIMEOUT).min(MAX_TIMEOUT);
+    let changed = matches!(
+        tokio::time::timeout(
+            Duration::from_secs(timeout),
+            receiver.wait_for(|current| *current != version),
+        )
+        .await,
+        Ok(Ok(_))
+    );
+    let current = *receiver.borrow();
+    (current, changed)
+}
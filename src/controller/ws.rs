@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        WebSocketUpgrade,
+    },
+    response::Response,
+};
+use hashbrown::{HashMap, HashSet};
+use tokio_stream::{wrappers::BroadcastStream, StreamExt, StreamMap};
+
+use crate::{
+    common::hub::StreamEvent,
+    context,
+    controller::api::hit,
+    error::{API_NOT_FOUND, API_NOT_SUBSCRIBED, APP_NOT_FOUND, JSON_IS_NO_VALID},
+    model::{
+        dto::WsDTO,
+        vo::{stream::StreamVO, ws::WsVO},
+    },
+    stream::make_vo,
+};
+
+/// 订阅的 api, 为空时表示订阅整个 app
+///
+/// Subscribed apis, the whole app is subscribed when empty
+type Subs = HashMap<String, Option<HashSet<String>>>;
+
+type Streams = StreamMap<String, BroadcastStream<Arc<StreamEvent>>>;
+
+/// WebSocket 连接, 可订阅 app 或 api 的变化并记录调用
+///
+/// WebSocket connection, subscribes to changes of apps or apis and records calls
+pub async fn ws(ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(handle)
+}
+
+async fn handle(mut socket: WebSocket) {
+    let mut subs = Subs::new();
+    let mut streams = Streams::new();
+    loop {
+        let reply = tokio::select! {
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<WsDTO>(&text) {
+                    Ok(dto) => handle_msg(dto, &mut subs, &mut streams),
+                    Err(e) => Some(WsVO::Error { code: JSON_IS_NO_VALID.0, msg: e.to_string() }),
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            // 推送通道已按间隔合并变化, 落后的连接直接跳过错过的事件
+            //
+            // Push channels already coalesce changes per interval, lagging connections skip missed events
+            Some((app, event)) = streams.next(), if !streams.is_empty() => match event {
+                Ok(event) => update(&app, &event.vo, &subs),
+                Err(_) => continue,
+            },
+        };
+        if let Some(reply) = reply {
+            let text = serde_json::to_string(&reply).unwrap();
+            if socket.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+fn handle_msg(dto: WsDTO, subs: &mut Subs, streams: &mut Streams) -> Option<WsVO> {
+    match dto {
+        WsDTO::Subscribe { app, api } => {
+            if !context!().apps.check_app(&app) {
+                return Some(APP_NOT_FOUND.into());
+            }
+            if let Some(api) = &api {
+                if !context!().apis.check_api(&app, api) {
+                    return Some(API_NOT_FOUND.into());
+                }
+            }
+            if !streams.contains_key(&app) {
+                let receiver = context!()
+                    .hub
+                    .subscribe(&app, || context!().apis.get_apis(&app));
+                streams.insert(app.to_owned(), BroadcastStream::new(receiver));
+            }
+            match (
+                subs.entry(app.to_owned()).or_insert(Some(HashSet::new())),
+                api,
+            ) {
+                (Some(apis), Some(api)) => {
+                    apis.insert(api);
+                }
+                (apis, None) => *apis = None,
+                (None, Some(_)) => {}
+            }
+            update(&app, &make_vo(&app, None).0, subs)
+        }
+        WsDTO::Unsubscribe { app, api } => {
+            let remove = match (subs.get_mut(&app), api) {
+                (Some(Some(apis)), Some(api)) => {
+                    apis.remove(&api);
+                    apis.is_empty()
+                }
+                (Some(_), None) => true,
+                // 订阅了整个 app 时不能只取消一个 api
+                //
+                // A single api cannot be unsubscribed while the whole app is subscribed
+                (Some(None), Some(_)) => return Some(API_NOT_SUBSCRIBED.into()),
+                _ => false,
+            };
+            if remove {
+                subs.remove(&app);
+                streams.remove(&app);
+            }
+            None
+        }
+        WsDTO::Incr { app, api } => Some(match hit(&app, &api) {
+            Ok(count) => WsVO::Incr { app, api, count },
+            Err(e) => <(i64, &str)>::from(e).into(),
+        }),
+    }
+}
+
+/// 只保留订阅的 api
+///
+/// Keep only the subscribed apis
+fn update(app: &str, vo: &StreamVO, subs: &Subs) -> Option<WsVO> {
+    let apis = match subs.get(app)? {
+        Some(apis) => vo
+            .apis
+            .iter()
+            .filter(|delta| apis.contains(&delta.api))
+            .cloned()
+            .collect(),
+        None => vo.apis.clone(),
+    };
+    if apis.is_empty() {
+        return None;
+    }
+    Some(WsVO::Update {
+        app: app.to_owned(),
+        total: vo.total,
+        apis,
+    })
+}
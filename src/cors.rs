@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use axum::http::{header::ACCESS_CONTROL_REQUEST_METHOD, request::Parts, HeaderValue, Method};
+use tower_http::cors::{
+    AllowCredentials, AllowHeaders, AllowMethods, AllowOrigin, CorsLayer, MaxAge,
+};
+
+use crate::config;
+
+/// 跨域规则适用的接口分组
+///
+/// Route group a CORS policy applies to
+#[derive(Clone, Copy)]
+pub enum Group {
+    /// 记录调用等供网页直接调用的接口
+    ///
+    /// Endpoints called directly from web pages, such as recording hits
+    Public,
+    /// 管理接口
+    ///
+    /// Management endpoints
+    Admin,
+}
+
+/// 从路径中取出 app, 如 `/api/<app>/<api>`
+///
+/// Get the app from the path, such as `/api/<app>/<api>`
+fn app(parts: &Parts) -> Option<&str> {
+    let mut segments = parts.uri.path().trim_start_matches('/').split('/');
+    match segments.next()? {
+        "api" | "limit" => segments.next(),
+        _ => None,
+    }
+}
+
+/// 计数与键值存储的公开读取与管理写入使用同一路径, 共用一个预检处理,
+/// 因此预检请求按要使用的方法选择分组: `/count/<key>` 的 GET 与 POST 以及键值存储的 GET 是公开的,
+/// 其余方法属于管理接口
+///
+/// Public reads and admin writes of counters and the key-value store share a path and so one
+/// preflight handler, a preflight request picks the group by the method it asks for: GET and POST
+/// of `/count/<key>` and GET of the key-value store are public, other methods are admin
+fn preflight_group(parts: &Parts) -> Option<Group> {
+    if parts.method != Method::OPTIONS {
+        return None;
+    }
+    let method = parts.headers.get(ACCESS_CONTROL_REQUEST_METHOD)?.as_bytes();
+    let mut segments = parts.uri.path().trim_start_matches('/').split('/');
+    let public = match (segments.next()?, segments.next()?, segments.next()) {
+        ("count", _, None) => method == b"GET" || method == b"POST",
+        ("bool" | "string" | "json", _, None) => method == b"GET",
+        _ => return None,
+    };
+    match public {
+        true => Some(Group::Public),
+        false => Some(Group::Admin),
+    }
+}
+
+fn is_allowed(origin: &HeaderValue, parts: &Parts, group: Group) -> bool {
+    let group = preflight_group(parts).unwrap_or(group);
+    let config = config::current();
+    let origins = match group {
+        Group::Public => app(parts)
+            .and_then(|app| config.cors_app_origins.get(app))
+            .unwrap_or(&config.cors_origins),
+        Group::Admin => &config.cors_admin_origins,
+    };
+    origins
+        .iter()
+        .any(|allowed| allowed == "*" || allowed.as_bytes() == origin.as_bytes())
+}
+
+/// 生成接口分组的跨域规则, 每个请求都读取运行中的配置, 重新加载配置后立即生效
+///
+/// Make the CORS policy of a route group, the running configuration is read on every request
+/// so a reload takes effect immediately
+pub fn layer(group: Group) -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, parts| {
+            is_allowed(origin, parts, group)
+        }))
+        .allow_methods(AllowMethods::mirror_request())
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(AllowCredentials::predicate(|_, _| {
+            config::current().cors_credentials
+        }))
+        .max_age(MaxAge::dynamic(|_, _| {
+            Duration::from_secs(config::current().cors_max_age)
+        }))
+}
@@ -1,4 +1,139 @@
-use crate::pool;
+use crate::{
+    model::{
+        Alert, Count, Flag, Gauge, GaugeSample, Histogram, Idempotency, Kv, Label, Limit, Quota,
+        Visitor,
+    },
+    pool,
+};
+
+/// 功能表, 每次启动时创建不存在的表
+///
+/// Feature tables, missing ones are created on every start
+pub const TABLES: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS "alerts" (
+        "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+        "app" text NOT NULL,
+        "api" text,
+        "kind" text NOT NULL,
+        "threshold" integer NOT NULL,
+        "window" integer NOT NULL,
+        "webhook" text,
+        "firing" integer NOT NULL DEFAULT 0
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "quotas" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "limit" integer NOT NULL,
+        "period" text NOT NULL,
+        PRIMARY KEY ("app", "api")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "limits" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "algorithm" text NOT NULL,
+        "capacity" integer NOT NULL,
+        "refill" real NOT NULL DEFAULT 0,
+        "window" integer NOT NULL DEFAULT 0,
+        PRIMARY KEY ("app", "api")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "visitors" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "day" integer NOT NULL,
+        "sketch" blob NOT NULL,
+        PRIMARY KEY ("app", "api", "day")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "salts" (
+        "day" integer NOT NULL PRIMARY KEY,
+        "salt" blob NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "labels" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "labels" text NOT NULL,
+        "count" integer NOT NULL,
+        PRIMARY KEY ("app", "api", "labels")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "histograms" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "time" integer NOT NULL,
+        "data" text NOT NULL,
+        PRIMARY KEY ("app", "api", "time")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "gauges" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "value" real NOT NULL,
+        PRIMARY KEY ("app", "api")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "gauge_samples" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "time" integer NOT NULL,
+        "min" real NOT NULL,
+        "max" real NOT NULL,
+        "sum" real NOT NULL,
+        "count" integer NOT NULL,
+        "last" real NOT NULL,
+        PRIMARY KEY ("app", "api", "time")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "counts" (
+        "key" text NOT NULL,
+        "count" integer NOT NULL,
+        PRIMARY KEY ("key")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "kv" (
+        "kind" text NOT NULL,
+        "key" text NOT NULL,
+        "value" text NOT NULL,
+        "version" integer NOT NULL,
+        "expires" integer,
+        PRIMARY KEY ("kind", "key")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "flags" (
+        "key" text NOT NULL,
+        "app" text NOT NULL,
+        "percentage" real NOT NULL,
+        "allow" text NOT NULL,
+        "deny" text NOT NULL,
+        PRIMARY KEY ("key")
+    );
+"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS "idempotency" (
+        "app" text NOT NULL,
+        "api" text NOT NULL,
+        "key" text NOT NULL,
+        "count" integer NOT NULL,
+        "expires" integer NOT NULL,
+        PRIMARY KEY ("app", "key")
+    );
+"#,
+];
 
 /// 更新app表中的api调用次数
 ///
@@ -34,6 +169,24 @@ pub async fn add_rec(app: &str, api: &str, time: &i64, count: &i64) {
         .unwrap();
 }
 
+/// 覆盖记录
+///
+/// Overwrite a record
+pub async fn set_rec(app: &str, api: &str, time: &i64, count: &i64) {
+    let app_e = bs58::encode(app.as_bytes()).into_string();
+    let api_e = bs58::encode(api.as_bytes()).into_string();
+    let sql = format!(
+        r#"insert into "{}_{}" (time, count) values (?, ?) on conflict(time) do update set count = excluded.count;"#,
+        app_e, api_e,
+    );
+    sqlx::query(&sql)
+        .bind(time)
+        .bind(count)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
+
 /// 新建 api 表
 ///
 /// Make a new api table
@@ -88,3 +241,554 @@ pub async fn make_app_table(app: &str) -> bool {
 
     true
 }
+
+/// 获取所有告警规则
+///
+/// Get all alert rules
+pub async fn get_alerts(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Alert> {
+    sqlx::query_as("select * from alerts")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 新增告警规则, 返回规则 id
+///
+/// Add an alert rule, returns the rule id
+pub async fn add_alert(alert: &Alert) -> i64 {
+    sqlx::query(
+        r#"insert into "alerts" (app, api, kind, threshold, window, webhook) values (?, ?, ?, ?, ?, ?);"#,
+    )
+    .bind(&alert.app)
+    .bind(&alert.api)
+    .bind(&alert.kind)
+    .bind(alert.threshold)
+    .bind(alert.window)
+    .bind(&alert.webhook)
+    .execute(pool!())
+    .await
+    .unwrap()
+    .last_insert_rowid()
+}
+
+/// 更新告警规则
+///
+/// Update an alert rule
+pub async fn update_alert(alert: &Alert) {
+    sqlx::query(
+        r#"update "alerts" set app = ?, api = ?, kind = ?, threshold = ?, window = ?, webhook = ?, firing = ? where id = ?;"#,
+    )
+    .bind(&alert.app)
+    .bind(&alert.api)
+    .bind(&alert.kind)
+    .bind(alert.threshold)
+    .bind(alert.window)
+    .bind(&alert.webhook)
+    .bind(alert.firing)
+    .bind(alert.id)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 更新告警状态, 规则在检查后被修改或删除时不更新, 返回是否更新
+///
+/// Update the firing state, nothing is updated if the rule was changed or removed after the check,
+/// returns whether it was updated
+pub async fn update_alert_firing(alert: &Alert) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"update "alerts" set firing = ? where id = ? and app = ? and api is ? and kind = ?
+        and threshold = ? and window = ? and webhook is ?;"#,
+    )
+    .bind(alert.firing)
+    .bind(alert.id)
+    .bind(&alert.app)
+    .bind(&alert.api)
+    .bind(&alert.kind)
+    .bind(alert.threshold)
+    .bind(alert.window)
+    .bind(&alert.webhook)
+    .execute(pool!())
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// 删除告警规则
+///
+/// Delete an alert rule
+pub async fn delete_alert(id: i64) {
+    sqlx::query(r#"delete from "alerts" where id = ?;"#)
+        .bind(id)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
+
+/// 获取 api 自 `since` 起的调用次数, 未指定 api 时为整个 app 的调用次数
+///
+/// Get the number of calls to the api since `since`, or to the whole app without an api
+pub async fn sum_rec(
+    pool: &sqlx::Pool<sqlx::Sqlite>,
+    app: &str,
+    apis: &[String],
+    since: i64,
+) -> i64 {
+    let app_e = bs58::encode(app.as_bytes()).into_string();
+    let mut sum = 0;
+    for api in apis {
+        let api_e = bs58::encode(api.as_bytes()).into_string();
+        let sql = format!(
+            r#"select coalesce(sum(count), 0) from "{}_{}" where time >= ?;"#,
+            app_e, api_e
+        );
+        // 新建的 api 在同步前还没有记录表
+        //
+        // Newly added apis have no record table before the sync
+        let count: (i64,) = sqlx::query_as(&sql)
+            .bind(since)
+            .fetch_one(pool)
+            .await
+            .unwrap_or((0,));
+        sum += count.0;
+    }
+    sum
+}
+
+/// 获取所有配额
+///
+/// Get all quotas
+pub async fn get_quotas(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Quota> {
+    sqlx::query_as("select * from quotas")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 新增或修改配额
+///
+/// Add or update a quota
+pub async fn set_quota(quota: &Quota) {
+    sqlx::query(
+        r#"insert into "quotas" (app, api, "limit", period) values (?, ?, ?, ?)
+        on conflict(app, api) do update set "limit" = excluded."limit", period = excluded.period;"#,
+    )
+    .bind(&quota.app)
+    .bind(&quota.api)
+    .bind(quota.limit)
+    .bind(&quota.period)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 删除配额
+///
+/// Delete a quota
+pub async fn delete_quota(app: &str, api: &str) {
+    sqlx::query(r#"delete from "quotas" where app = ? and api = ?;"#)
+        .bind(app)
+        .bind(api)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
+
+/// 获取所有限流规则
+///
+/// Get all rate limit rules
+pub async fn get_limits(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Limit> {
+    sqlx::query_as("select * from limits")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 新增或修改限流规则
+///
+/// Add or update a rate limit rule
+pub async fn set_limit(limit: &Limit) {
+    sqlx::query(
+        r#"insert into "limits" (app, api, algorithm, capacity, refill, window) values (?, ?, ?, ?, ?, ?)
+        on conflict(app, api) do update set algorithm = excluded.algorithm, capacity = excluded.capacity,
+        refill = excluded.refill, window = excluded.window;"#,
+    )
+    .bind(&limit.app)
+    .bind(&limit.api)
+    .bind(&limit.algorithm)
+    .bind(limit.capacity)
+    .bind(limit.refill)
+    .bind(limit.window)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 删除限流规则
+///
+/// Delete a rate limit rule
+pub async fn delete_limit(app: &str, api: &str) {
+    sqlx::query(r#"delete from "limits" where app = ? and api = ?;"#)
+        .bind(app)
+        .bind(api)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
+
+/// 获取某天的所有访客草图
+///
+/// Get all visitor sketches of a day
+pub async fn get_visitors_day(pool: &sqlx::Pool<sqlx::Sqlite>, day: i64) -> Vec<Visitor> {
+    sqlx::query_as("select * from visitors where day = ?")
+        .bind(day)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 获取 `from` 至 `to` 日期内的访客草图, 未指定 api 时为整个 app 的草图
+///
+/// Get the visitor sketches from `from` to `to` days, or of the whole app without an api
+pub async fn get_visitors(app: &str, api: Option<&str>, from: i64, to: i64) -> Vec<Visitor> {
+    sqlx::query_as(
+        r#"select * from "visitors" where app = ? and (? is null or api = ?) and day >= ? and day <= ?;"#,
+    )
+    .bind(app)
+    .bind(api)
+    .bind(api)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool!())
+    .await
+    .unwrap()
+}
+
+/// 获取某天生成访客标识的盐
+///
+/// Get the salt used to derive visitors on a day
+pub async fn get_salt(pool: &sqlx::Pool<sqlx::Sqlite>, day: i64) -> Option<Vec<u8>> {
+    sqlx::query_scalar(r#"select salt from "salts" where day = ?;"#)
+        .bind(day)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+}
+
+/// 保存某天的盐, 并删除以前的盐
+///
+/// Save the salt of a day, and delete the earlier salts
+pub async fn set_salt(day: i64, salt: &[u8]) {
+    sqlx::query(
+        r#"insert into "salts" (day, salt) values (?, ?)
+        on conflict(day) do update set salt = excluded.salt;"#,
+    )
+    .bind(day)
+    .bind(salt)
+    .execute(pool!())
+    .await
+    .unwrap();
+    sqlx::query(r#"delete from "salts" where day < ?;"#)
+        .bind(day)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
+
+/// 新增或覆盖访客草图
+///
+/// Add or replace a visitor sketch
+pub async fn set_visitor(app: &str, api: &str, day: i64, sketch: &[u8]) {
+    sqlx::query(
+        r#"insert into "visitors" (app, api, day, sketch) values (?, ?, ?, ?)
+        on conflict(app, api, day) do update set sketch = excluded.sketch;"#,
+    )
+    .bind(app)
+    .bind(api)
+    .bind(day)
+    .bind(sketch)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 获取所有标签组合的调用次数
+///
+/// Get the number of calls of all label combinations
+pub async fn get_labels(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Label> {
+    sqlx::query_as("select * from labels")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 更新标签组合的调用次数
+///
+/// Update the number of calls of a label combination
+pub async fn set_label(app: &str, api: &str, labels: &str, count: i64) {
+    sqlx::query(
+        r#"insert into "labels" (app, api, labels, count) values (?, ?, ?, ?)
+        on conflict(app, api, labels) do update set count = excluded.count;"#,
+    )
+    .bind(app)
+    .bind(api)
+    .bind(labels)
+    .bind(count)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 获取某小时的所有直方图
+///
+/// Get all histograms of an hour
+pub async fn get_histograms_hour(pool: &sqlx::Pool<sqlx::Sqlite>, time: i64) -> Vec<Histogram> {
+    sqlx::query_as("select * from histograms where time = ?")
+        .bind(time)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 获取 api 在 `from` 至 `to` 小时内的直方图
+///
+/// Get the histograms of the api from `from` to `to` hours
+pub async fn get_histograms(app: &str, api: &str, from: i64, to: i64) -> Vec<Histogram> {
+    sqlx::query_as(
+        r#"select * from "histograms" where app = ? and api = ? and time >= ? and time <= ?;"#,
+    )
+    .bind(app)
+    .bind(api)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool!())
+    .await
+    .unwrap()
+}
+
+/// 新增或覆盖直方图
+///
+/// Add or replace a histogram
+pub async fn set_histogram(app: &str, api: &str, time: i64, data: &str) {
+    sqlx::query(
+        r#"insert into "histograms" (app, api, time, data) values (?, ?, ?, ?)
+        on conflict(app, api, time) do update set data = excluded.data;"#,
+    )
+    .bind(app)
+    .bind(api)
+    .bind(time)
+    .bind(data)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 获取所有仪表的当前值
+///
+/// Get the current values of all gauges
+pub async fn get_gauges(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Gauge> {
+    sqlx::query_as("select * from gauges")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 更新仪表的当前值
+///
+/// Update the current value of a gauge
+pub async fn set_gauge(app: &str, api: &str, value: f64) {
+    sqlx::query(
+        r#"insert into "gauges" (app, api, value) values (?, ?, ?)
+        on conflict(app, api) do update set value = excluded.value;"#,
+    )
+    .bind(app)
+    .bind(api)
+    .bind(value)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 获取仪表在 `from` 至 `to` 分钟内的采样
+///
+/// Get the samples of a gauge from `from` to `to` minutes
+pub async fn get_gauge_samples(app: &str, api: &str, from: i64, to: i64) -> Vec<GaugeSample> {
+    sqlx::query_as(
+        r#"select * from "gauge_samples" where app = ? and api = ? and time >= ? and time <= ?;"#,
+    )
+    .bind(app)
+    .bind(api)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool!())
+    .await
+    .unwrap()
+}
+
+/// 新增采样, 与同一分钟已有的采样合并
+///
+/// Add samples, merged with the existing samples of the same minute
+pub async fn add_gauge_sample(sample: &GaugeSample) {
+    sqlx::query(
+        r#"insert into "gauge_samples" (app, api, time, min, max, sum, count, last) values (?, ?, ?, ?, ?, ?, ?, ?)
+        on conflict(app, api, time) do update set min = min(min, excluded.min), max = max(max, excluded.max),
+        sum = sum + excluded.sum, count = count + excluded.count, last = excluded.last;"#,
+    )
+    .bind(&sample.app)
+    .bind(&sample.api)
+    .bind(sample.time)
+    .bind(sample.min)
+    .bind(sample.max)
+    .bind(sample.sum)
+    .bind(sample.count)
+    .bind(sample.last)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 获取所有以 key 记录的计数
+///
+/// Get all counters keyed by keys
+pub async fn get_counts(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Count> {
+    sqlx::query_as("select * from counts")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 更新以 key 记录的计数
+///
+/// Update a counter keyed by a key
+pub async fn set_count(key: &str, count: i64) {
+    sqlx::query(
+        r#"insert into "counts" (key, count) values (?, ?)
+        on conflict(key) do update set count = excluded.count;"#,
+    )
+    .bind(key)
+    .bind(count)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 获取键值存储中所有的值
+///
+/// Get all values in the key-value store
+pub async fn get_kv(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Kv> {
+    sqlx::query_as("select * from kv")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 新增或覆盖键值存储中的值
+///
+/// Add or replace a value in the key-value store
+pub async fn set_kv(kv: &Kv) {
+    sqlx::query(
+        r#"insert into "kv" (kind, key, value, version, expires) values (?, ?, ?, ?, ?)
+        on conflict(kind, key) do update set value = excluded.value, version = excluded.version,
+        expires = excluded.expires;"#,
+    )
+    .bind(&kv.kind)
+    .bind(&kv.key)
+    .bind(&kv.value)
+    .bind(kv.version)
+    .bind(kv.expires)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 删除键值存储中的值
+///
+/// Delete a value in the key-value store
+pub async fn delete_kv(kind: &str, key: &str) {
+    sqlx::query(r#"delete from "kv" where kind = ? and key = ?;"#)
+        .bind(kind)
+        .bind(key)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
+
+/// 获取所有功能开关
+///
+/// Get all feature flags
+pub async fn get_flags(pool: &sqlx::Pool<sqlx::Sqlite>) -> Vec<Flag> {
+    sqlx::query_as("select * from flags")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 新增或修改功能开关
+///
+/// Add or update a feature flag
+pub async fn set_flag(flag: &Flag) {
+    sqlx::query(
+        r#"insert into "flags" (key, app, percentage, allow, deny) values (?, ?, ?, ?, ?)
+        on conflict(key) do update set app = excluded.app, percentage = excluded.percentage,
+        allow = excluded.allow, deny = excluded.deny;"#,
+    )
+    .bind(&flag.key)
+    .bind(&flag.app)
+    .bind(flag.percentage)
+    .bind(&flag.allow)
+    .bind(&flag.deny)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 删除功能开关
+///
+/// Delete a feature flag
+pub async fn delete_flag(key: &str) {
+    sqlx::query(r#"delete from "flags" where key = ?;"#)
+        .bind(key)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
+
+/// 获取所有未过期的幂等键, 按过期时间排序
+///
+/// Get all idempotency keys that have not expired, sorted by expiry
+pub async fn get_idempotency(pool: &sqlx::Pool<sqlx::Sqlite>, now: i64) -> Vec<Idempotency> {
+    sqlx::query_as("select * from idempotency where expires > ? order by expires")
+        .bind(now)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// 新增或覆盖幂等键
+///
+/// Add or replace an idempotency key
+pub async fn set_idempotency(idempotency: &Idempotency) {
+    sqlx::query(
+        r#"insert into "idempotency" (app, api, key, count, expires) values (?, ?, ?, ?, ?)
+        on conflict(app, key) do update set api = excluded.api, count = excluded.count,
+        expires = excluded.expires;"#,
+    )
+    .bind(&idempotency.app)
+    .bind(&idempotency.api)
+    .bind(&idempotency.key)
+    .bind(idempotency.count)
+    .bind(idempotency.expires)
+    .execute(pool!())
+    .await
+    .unwrap();
+}
+
+/// 删除过期的幂等键
+///
+/// Delete the expired idempotency keys
+pub async fn delete_idempotency(now: i64) {
+    sqlx::query(r#"delete from "idempotency" where expires <= ?;"#)
+        .bind(now)
+        .execute(pool!())
+        .await
+        .unwrap();
+}
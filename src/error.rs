@@ -4,3 +4,45 @@ pub const APP_NOT_FOUND: (i64, &str) = (1003, "App not found");
 pub const API_NAME_IS_NO_VALID: (i64, &str) = (1004, "Api name is not valid");
 pub const API_ALREADY_EXISTS: (i64, &str) = (1005, "Api already exists");
 pub const API_NOT_FOUND: (i64, &str) = (1006, "Api not found");
+pub const IMPORT_DATA_IS_NO_VALID: (i64, &str) = (1007, "Import data is not valid");
+pub const IMPORT_MODE_IS_NO_VALID: (i64, &str) = (1008, "Import mode is not valid");
+pub const SNAPSHOT_FAILED: (i64, &str) = (1009, "Snapshot failed");
+pub const JSON_IS_NO_VALID: (i64, &str) = (1010, "Json is not valid");
+pub const ALERT_KIND_IS_NO_VALID: (i64, &str) = (1011, "Alert kind is not valid");
+pub const ALERT_WINDOW_IS_NO_VALID: (i64, &str) = (1012, "Alert window is not valid");
+pub const ALERT_NOT_FOUND: (i64, &str) = (1013, "Alert not found");
+pub const QUOTA_EXCEEDED: (i64, &str) = (1014, "Quota exceeded");
+pub const QUOTA_PERIOD_IS_NO_VALID: (i64, &str) = (1015, "Quota period is not valid");
+pub const QUOTA_NOT_FOUND: (i64, &str) = (1016, "Quota not found");
+pub const LIMIT_IS_NO_VALID: (i64, &str) = (1017, "Limit is not valid");
+pub const LIMIT_NOT_FOUND: (i64, &str) = (1018, "Limit not found");
+pub const LIMIT_KEY_IS_NO_VALID: (i64, &str) = (1019, "Limit key is not valid");
+pub const VISITOR_RANGE_IS_NO_VALID: (i64, &str) = (1020, "Visitor range is not valid");
+pub const LABEL_IS_NO_VALID: (i64, &str) = (1021, "Label is not valid");
+pub const LABEL_LIMIT_EXCEEDED: (i64, &str) = (1022, "Label limit exceeded");
+pub const HISTOGRAM_VALUE_IS_NO_VALID: (i64, &str) = (1023, "Histogram value is not valid");
+pub const HISTOGRAM_RANGE_IS_NO_VALID: (i64, &str) = (1024, "Histogram range is not valid");
+pub const GAUGE_NOT_FOUND: (i64, &str) = (1025, "Gauge not found");
+pub const GAUGE_VALUE_IS_NO_VALID: (i64, &str) = (1026, "Gauge value is not valid");
+pub const GAUGE_RANGE_IS_NO_VALID: (i64, &str) = (1027, "Gauge range is not valid");
+pub const COUNT_NOT_FOUND: (i64, &str) = (1028, "Count not found");
+pub const COUNT_KEY_IS_NO_VALID: (i64, &str) = (1029, "Count key is not valid");
+pub const KV_KEY_IS_NO_VALID: (i64, &str) = (1030, "Key is not valid");
+pub const KV_VALUE_IS_NO_VALID: (i64, &str) = (1031, "Value is not valid");
+pub const KV_NOT_FOUND: (i64, &str) = (1032, "Key not found");
+pub const KV_VERSION_CONFLICT: (i64, &str) = (1033, "Version conflict");
+pub const FLAG_NOT_FOUND: (i64, &str) = (1034, "Flag not found");
+pub const FLAG_IS_NO_VALID: (i64, &str) = (1035, "Flag is not valid");
+pub const FLAG_USER_IS_NO_VALID: (i64, &str) = (1036, "Flag user is not valid");
+pub const IDEMPOTENCY_KEY_IS_NO_VALID: (i64, &str) = (1037, "Idempotency key is not valid");
+pub const IDEMPOTENCY_KEY_IN_PROGRESS: (i64, &str) = (1038, "Idempotency key is in progress");
+pub const CONFIG_IS_NO_VALID: (i64, &str) = (1039, "Configuration is not valid");
+pub const SERVICE_NOT_READY: (i64, &str) = (1040, "Service is not ready");
+pub const IMPORT_DISABLED: (i64, &str) = (1041, "Import is disabled");
+pub const API_NOT_SUBSCRIBED: (i64, &str) = (1042, "Api is not subscribed, the whole app is");
+pub const ALERT_WEBHOOK_IS_NO_VALID: (i64, &str) = (1043, "Alert webhook is not valid");
+pub const QUOTA_LIMIT_IS_NO_VALID: (i64, &str) = (1044, "Quota limit is not valid");
+pub const LIMIT_KEYS_EXCEEDED: (i64, &str) = (1045, "Limit keys exceeded");
+pub const COUNT_OVERFLOW: (i64, &str) = (1046, "Count overflow");
+pub const IDEMPOTENCY_KEY_API_MISMATCH: (i64, &str) =
+    (1047, "Idempotency key is used by another api");
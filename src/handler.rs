@@ -5,7 +5,7 @@ use axum::{
 };
 use axum_macros::FromRequest;
 
-use crate::resp::Resp;
+use crate::{error::JSON_IS_NO_VALID, resp::Resp};
 
 #[derive(FromRequest)]
 #[from_request(via(axum::Json), rejection(CustomRejection))]
@@ -17,7 +17,10 @@ impl From<JsonRejection> for CustomRejection {
     fn from(rejection: JsonRejection) -> Self {
         let response = (
             StatusCode::INTERNAL_SERVER_ERROR,
-            axum::Json(Resp::<String>::fail((1010, &format!("{rejection:?}")))),
+            axum::Json(Resp::<String>::fail((
+                JSON_IS_NO_VALID.0,
+                &format!("{rejection:?}"),
+            ))),
         )
             .into_response();
 
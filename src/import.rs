@@ -0,0 +1,183 @@
+use anyhow::{anyhow, bail, Result};
+use hashbrown::{HashMap, HashSet};
+use tracing::info;
+
+use crate::{
+    common::watch::Target,
+    context,
+    controller::{api as Api, app as App},
+    db::{add_rec, set_rec, update_count},
+    model::{dto::ImportDTO, vo::import::ImportVO},
+    sync::flush,
+    util,
+};
+
+/// 合并方式
+///
+/// Merge mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// 累加到已有的值上
+    ///
+    /// Add to the existing values
+    Add,
+    /// 覆盖已有的值
+    ///
+    /// Replace the existing values
+    Replace,
+}
+
+impl Mode {
+    pub fn parse(mode: Option<&str>) -> Option<Self> {
+        match mode {
+            None | Some("add") => Some(Mode::Add),
+            Some("replace") => Some(Mode::Replace),
+            _ => None,
+        }
+    }
+}
+
+/// 解析导入数据, 支持 NDJSON 与带表头的 CSV
+///
+/// Parse import data, NDJSON and CSV with a header are supported
+pub fn parse(data: &str) -> Result<Vec<ImportDTO>> {
+    let mut lines = data
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let first = match lines.next() {
+        Some(first) => first,
+        None => return Ok(vec![]),
+    };
+
+    let rows = if first.1.starts_with('{') {
+        std::iter::once(first)
+            .chain(lines)
+            .map(|(n, line)| {
+                serde_json::from_str::<ImportDTO>(line).map_err(|e| anyhow!("line {}: {}", n, e))
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        // CSV 表头决定列的顺序, 多余的列会被忽略
+        //
+        // The CSV header decides the column order, extra columns are ignored
+        let header: Vec<&str> = first.1.split(',').map(str::trim).collect();
+        let column = |name: &str| header.iter().position(|h| *h == name);
+        let app_i = column("app").ok_or_else(|| anyhow!("line {}: missing app column", first.0))?;
+        let (api_i, time_i, count_i) = (column("api"), column("time"), column("count"));
+
+        lines
+            .map(|(n, line)| {
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                let field = |i: Option<usize>| {
+                    i.and_then(|i| fields.get(i))
+                        .copied()
+                        .filter(|f| !f.is_empty())
+                };
+                let number = |i: Option<usize>| {
+                    field(i)
+                        .map(|f| f.parse::<i64>())
+                        .transpose()
+                        .map_err(|e| anyhow!("line {}: {}", n, e))
+                };
+                Ok(ImportDTO {
+                    app: field(Some(app_i)).unwrap_or_default().to_owned(),
+                    api: field(api_i).map(str::to_owned),
+                    time: number(time_i)?,
+                    count: number(count_i)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    for (n, row) in rows.iter().enumerate() {
+        if !util::is_valid(&row.app) {
+            bail!("row {}: app name is not valid", n + 1);
+        }
+        match &row.api {
+            Some(api) if !util::is_valid(api) => bail!("row {}: api name is not valid", n + 1),
+            None if row.time.is_some() || row.count.is_some() => {
+                bail!("row {}: api is required with time or count", n + 1)
+            }
+            _ => {}
+        }
+        if row.time.is_some() && row.count.is_none() {
+            bail!("row {}: count is required with time", n + 1);
+        }
+    }
+
+    Ok(rows)
+}
+
+/// 导入数据, 通过与 App::add 和 Api::add 相同的路径创建 app 与 api
+///
+/// Import data, apps and apis are created through the same paths as App::add and Api::add
+pub async fn run(rows: Vec<ImportDTO>, mode: Mode) -> ImportVO {
+    let mut vo = ImportVO::default();
+    let mut totals: HashSet<(String, String)> = HashSet::new();
+    let mut records: HashMap<(String, String), HashMap<i64, i64>> = HashMap::new();
+
+    for row in rows {
+        if !context!().apps.check_app(&row.app) {
+            App::create(&row.app);
+            vo.apps += 1;
+        }
+        let api = match row.api {
+            Some(api) => api,
+            None => continue,
+        };
+        if !context!().apis.check_api(&row.app, &api) {
+            Api::create(&row.app, &api);
+            vo.apis += 1;
+        }
+
+        match (row.time, row.count) {
+            (Some(time), Some(count)) => {
+                let times = records.entry((row.app, api)).or_default();
+                match mode {
+                    Mode::Add => *times.entry(time).or_default() += count,
+                    Mode::Replace => {
+                        times.insert(time, count);
+                    }
+                }
+                vo.records += 1;
+            }
+            (None, Some(count)) => {
+                match mode {
+                    Mode::Add => {
+                        context!().apis.add(&row.app, &api, count);
+                    }
+                    Mode::Replace => context!().apis.set(&row.app, &api, count),
+                }
+                context!().watches.notify(Target::Api(&row.app, &api));
+                totals.insert((row.app, api));
+                vo.totals += 1;
+            }
+            _ => {}
+        }
+    }
+
+    // 先写入新建的 app 与 api, 保证相关表存在
+    //
+    // Write the new apps and apis first to make sure the related tables exist
+    flush().await;
+
+    for ((app, api), times) in records.iter() {
+        for (time, count) in times.iter() {
+            match mode {
+                Mode::Add => add_rec(app, api, time, count).await,
+                Mode::Replace => set_rec(app, api, time, count).await,
+            }
+        }
+    }
+
+    for (app, api) in totals.iter() {
+        update_count(app, api, &context!().apis.get_api(app, api)).await;
+    }
+
+    info!("Import finished: {:?}", vo);
+
+    vo
+}
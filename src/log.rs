@@ -1,78 +1,145 @@
-use time::{macros::format_description, UtcOffset};
-use tracing::{metadata::LevelFilter, subscriber};
+use std::io::IsTerminal;
+
+use once_cell::sync::OnceCell;
+use time::{format_description::FormatItem, macros::format_description, UtcOffset};
+use tracing::{metadata::LevelFilter, subscriber, warn, Subscriber};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
 use tracing_subscriber::{
-    filter::Targets, fmt::time::OffsetTime, prelude::__tracing_subscriber_SubscriberExt,
+    filter::Targets,
+    fmt::{time::OffsetTime, MakeWriter},
+    prelude::__tracing_subscriber_SubscriberExt,
+    registry::LookupSpan,
+    reload, Layer, Registry,
 };
 
-pub fn init() -> tracing_appender::non_blocking::WorkerGuard {
+use crate::config::CONFIG;
+
+/// 日志级别, 可以在重新加载配置时修改
+///
+/// Log level, can be changed when the configuration is reloaded
+static LEVEL: OnceCell<reload::Handle<LevelFilter, Registry>> = OnceCell::new();
+
+type Timer = OffsetTime<&'static [FormatItem<'static>]>;
+
+fn parse_level(level: &str) -> LevelFilter {
+    match level {
+        "trace" => LevelFilter::TRACE,
+        "debug" => LevelFilter::DEBUG,
+        "info" => LevelFilter::INFO,
+        "warn" => LevelFilter::WARN,
+        "error" => LevelFilter::ERROR,
+        _ => LevelFilter::INFO,
+    }
+}
+
+/// 修改日志级别
+///
+/// Change the log level
+pub fn set_level(level: &str) {
+    if let Some(handle) = LEVEL.get() {
+        if let Err(e) = handle.reload(parse_level(level)) {
+            warn!("Failed to change log level: {}", e);
+        }
+    }
+}
+
+/// 生成一个输出位置的日志层
+///
+/// Make the log layer of one output
+fn layer<S, W>(writer: W, timer: Timer, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_timer(timer)
+        .with_writer(writer);
+    match CONFIG.log_json {
+        true => fmt.json().boxed(),
+        false => fmt.with_ansi(ansi).boxed(),
+    }
+}
+
+/// 初始化日志, 需要在启动运行时之前调用, 此时只有一个线程, 才能读取系统时区
+///
+/// Initialize logging, must be called before the runtime starts, as the system time zone
+/// can only be read while there is a single thread
+pub fn init() -> Vec<WorkerGuard> {
     // 设置时区
     //
     // Set time zone
-    let local_time = OffsetTime::new(
-        UtcOffset::from_hms(8, 0, 0).unwrap(),
-        format_description!("[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]"),
+    let offset = CONFIG.log_timezone.unwrap_or_else(|| {
+        UtcOffset::current_local_offset().unwrap_or_else(|_| {
+            eprintln!("Failed to get the local time zone, using UTC");
+            UtcOffset::UTC
+        })
+    });
+    let timer = OffsetTime::new(
+        offset,
+        format_description!(
+            "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3][offset_hour sign:mandatory]:[offset_minute]"
+        ),
     );
 
-    let fmt = tracing_subscriber::fmt().with_timer(local_time);
+    let mut layers = vec![];
+    let mut guards = vec![];
 
-    // 如果是debug模式，日志输出到控制台，否则输出到文件
+    // 输出到标准输出, 便于在容器中收集日志
     //
-    // If it is debug mode, the log is output to the console, otherwise it is output to the file
-    #[cfg(debug_assertions)]
-    let (fmt, guard) = {
+    // Output to stdout, so the log can be collected in containers
+    if CONFIG.log_stdout {
         let (non_blocking, guard) = tracing_appender::non_blocking(std::io::stdout());
-        let fmt = fmt
-            .with_max_level(tracing::Level::DEBUG)
-            .with_ansi(true)
-            .with_writer(non_blocking)
-            .pretty();
-        (fmt, guard)
-    };
-
-    #[cfg(not(debug_assertions))]
-    let (fmt, guard) = {
-        use super::CONFIG;
-
-        let log_level = match &CONFIG.log_level[..] {
-            "trace" => tracing::Level::TRACE,
-            "debug" => tracing::Level::DEBUG,
-            "info" => tracing::Level::INFO,
-            "warn" => tracing::Level::WARN,
-            "error" => tracing::Level::ERROR,
-            _ => tracing::Level::INFO,
-        };
+        layers.push(layer(
+            non_blocking,
+            timer.clone(),
+            std::io::stdout().is_terminal(),
+        ));
+        guards.push(guard);
+    }
 
-        let exe_path = std::env::current_exe().expect("Failed to get current executable");
-        let exe_dir = exe_path
-            .parent()
-            .expect("Failed to get executable directory");
-        let log_dir = exe_dir.join("logs");
+    // 输出到文件, 按配置分割并清理旧的文件
+    //
+    // Output to files, split and cleaned up by the configuration
+    if CONFIG.log_file {
+        let log_dir = &CONFIG.log_dir;
 
         if !log_dir.exists() {
-            std::fs::create_dir(&log_dir).expect("Failed to create log directory");
+            std::fs::create_dir_all(log_dir).expect("Failed to create log directory");
         }
-        let log_file_name = format!("{}.log", &CONFIG.server_name);
-        let file_appender = match &CONFIG.log_split[..] {
-            "hour" => tracing_appender::rolling::hourly(log_dir, &log_file_name),
-            "minute" => tracing_appender::rolling::minutely(log_dir, &log_file_name),
-            _ => tracing_appender::rolling::daily(log_dir, &log_file_name),
+        let rotation = match &CONFIG.log_split[..] {
+            "hour" => Rotation::HOURLY,
+            "minute" => Rotation::MINUTELY,
+            _ => Rotation::DAILY,
         };
+        let mut builder = RollingFileAppender::builder()
+            .rotation(rotation)
+            .filename_prefix(format!("{}.log", &CONFIG.server_name));
+        if CONFIG.max_log_files > 0 {
+            builder = builder.max_log_files(CONFIG.max_log_files);
+        }
+        let file_appender = builder.build(log_dir).expect("Failed to create log file");
 
         let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
-        let fmt = fmt
-            .with_max_level(log_level)
-            .with_ansi(false)
-            .with_writer(non_blocking);
-        (fmt, guard)
-    };
+        layers.push(layer(non_blocking, timer, false));
+        guards.push(guard);
+    }
+
+    let (level, handle) = reload::Layer::new(parse_level(&CONFIG.log_level));
+    let _ = LEVEL.set(handle);
 
     let targets = Targets::new()
         .with_target("h2", LevelFilter::OFF)
-        .with_default(LevelFilter::DEBUG);
+        .with_default(LevelFilter::TRACE);
 
-    let fmt = fmt.finish().with(targets);
+    let subscriber = tracing_subscriber::registry()
+        .with(level)
+        .with(layers)
+        .with(targets);
 
-    subscriber::set_global_default(fmt).unwrap();
+    subscriber::set_global_default(subscriber).unwrap();
 
-    guard
+    guards
 }
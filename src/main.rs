@@ -1,47 +1,173 @@
-use anyhow::{Ok, Result};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, Ok, Result};
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    routing::{get, post, put},
     Router,
 };
 use common::{init, CONTEXT};
 use config::CONFIG;
-use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 
 use crate::{
-    controller::{api as Api, app as App},
+    alert::alert_task,
+    controller::{
+        admin as Admin, alert as Alert, api as Api, app as App, count as Count, flag as Flag,
+        gauge as Gauge, health as Health, histogram as Histogram, kv as Kv, limit as Limit,
+        quota as Quota, stream as Stream, visitor as Visitor, watch as Watch, ws as Ws,
+    },
+    cors::Group,
+    import::Mode,
+    snapshot::snapshot_task,
+    stream::stream_task,
     sync::db_sync,
 };
 
+mod alert;
 mod common;
 mod config;
 mod controller;
+mod cors;
 mod db;
 mod error;
 mod handler;
+mod import;
 mod log;
 mod model;
 mod resp;
+mod server;
+mod snapshot;
+mod stream;
 mod sync;
 mod util;
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
+    // 在启动运行时之前初始化日志
+    //
+    // Initialize logging before the runtime starts
+    let _guards = log::init();
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run())
+}
+
+async fn run() -> Result<()> {
     CONTEXT.get_or_init(init).await;
 
-    let _guard = log::init();
+    // 命令行子命令
+    //
+    // Command line subcommands
+    let args = &CONFIG.args;
+    match args.first().map(String::as_str) {
+        // apirec import <file> [--replace]
+        Some("import") => {
+            let file = args
+                .get(1)
+                .ok_or_else(|| anyhow!("Usage: apirec import <file> [--replace]"))?;
+            let mode = match args.iter().any(|arg| arg == "--replace") {
+                true => Mode::Replace,
+                false => Mode::Add,
+            };
+            let rows = import::parse(&std::fs::read_to_string(file)?)?;
+            let vo = import::run(rows, mode).await;
+            println!("{}", serde_json::to_string(&vo)?);
+            return Ok(());
+        }
+        // apirec snapshot [path]
+        //
+        // 不包含运行中的服务尚未写入的数据, 服务运行时应使用 `POST /admin/snapshot`
+        //
+        // Does not include what a running server has not written yet, use `POST /admin/snapshot`
+        // while the server is running
+        Some("snapshot") => {
+            let path = snapshot::snapshot(args.get(1).map(PathBuf::from)).await?;
+            println!("{}", path.display());
+            return Ok(());
+        }
+        _ => {}
+    }
 
-    let app = Router::new()
+    // 供网页直接调用的接口, 跨域来源可以按 app 设置
+    //
+    // Endpoints called directly from web pages, cross-origin sources can be set per app
+    let public = Router::new()
         .route("/", get(|| async { "Hello, World!" }))
+        .route("/api/:app/:api", get(Api::get).post(Api::post))
+        .route("/api/:app/:api/watch", get(Watch::api))
+        .route("/limit/:app/:api/:key", post(Limit::check))
+        .route("/flag/:key/eval", post(Flag::evaluate))
+        .route("/count/:key", get(Count::get).post(Count::add))
+        .route("/count/:key/watch", get(Watch::count))
+        .route("/bool/:key", get(Kv::get_bool))
+        .route("/bool/:key/watch", get(Watch::bool))
+        .route("/string/:key", get(Kv::get_string))
+        .route("/string/:key/watch", get(Watch::string))
+        .route("/json/:key", get(Kv::get_json))
+        .route("/json/:key/watch", get(Watch::json))
+        .layer(cors::layer(Group::Public));
+
+    // 管理接口
+    //
+    // Management endpoints
+    let admin = Router::new()
         .route("/api", post(App::add))
         .route("/api/:app", get(App::get).post(Api::add))
-        .route("/api/:app/:api", get(Api::get).post(Api::post))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        );
+        .route("/visitors/:app", get(Visitor::get_app))
+        .route("/stream/:app", get(Stream::stream))
+        .route("/api/:app/:api/visitors", get(Visitor::get_api))
+        .route("/api/:app/:api/histogram", get(Histogram::get))
+        .route("/ws", get(Ws::ws))
+        .route("/alert", get(Alert::list).post(Alert::add))
+        .route(
+            "/alert/:id",
+            get(Alert::get).put(Alert::update).delete(Alert::delete),
+        )
+        .route("/quota", get(Quota::list))
+        .route(
+            "/quota/:app",
+            get(Quota::get_app)
+                .put(Quota::set_app)
+                .delete(Quota::delete_app),
+        )
+        .route(
+            "/quota/:app/:api",
+            get(Quota::get_api)
+                .put(Quota::set_api)
+                .delete(Quota::delete_api),
+        )
+        .route(
+            "/limit/:app/:api",
+            get(Limit::get).put(Limit::set).delete(Limit::delete),
+        )
+        .route("/count/:key", put(Count::set))
+        .route("/bool/:key", post(Kv::set_bool).delete(Kv::delete_bool))
+        .route(
+            "/string/:key",
+            post(Kv::set_string).delete(Kv::delete_string),
+        )
+        .route("/json/:key", post(Kv::set_json).delete(Kv::delete_json))
+        .route(
+            "/flag/:key",
+            get(Flag::get).put(Flag::set).delete(Flag::delete),
+        )
+        .route("/gauge/:app/:api", get(Gauge::get).put(Gauge::set))
+        .route("/gauge/:app/:api/inc", post(Gauge::inc))
+        .route("/gauge/:app/:api/dec", post(Gauge::dec))
+        .route(
+            "/admin/import",
+            post(Admin::import).layer(DefaultBodyLimit::max(Admin::IMPORT_BODY_LIMIT)),
+        )
+        .route("/admin/snapshot", post(Admin::snapshot))
+        .route("/admin/reload", post(Admin::reload))
+        .route("/admin/status", get(Admin::status))
+        .route("/healthz", get(Health::healthz))
+        .route("/readyz", get(Health::readyz))
+        .layer(cors::layer(Group::Admin));
+
+    let app = public.merge(admin);
 
     // 数据库同步任务
     //
@@ -50,10 +176,87 @@ async fn main() -> Result<()> {
         db_sync().await;
     });
 
-    info!("Server started at {}", CONFIG.server_url);
+    // 告警检查任务
+    //
+    // Alert check task
+    tokio::spawn(async {
+        alert_task().await;
+    });
+
+    // 实时推送任务
+    //
+    // Live push task
+    tokio::spawn(async {
+        stream_task().await;
+    });
+
+    // 定时快照任务
+    //
+    // Scheduled snapshot task
+    tokio::spawn(async {
+        snapshot_task().await;
+    });
+
+    // 收到 SIGHUP 时重新加载配置
+    //
+    // Reload the configuration on SIGHUP
+    #[cfg(unix)]
+    tokio::spawn(config::reload_task());
+
+    // Unix 套接字, 可以与 TCP 端口同时监听
+    //
+    // Unix socket, can be served together with the TCP port
+    #[cfg(unix)]
+    let unix = {
+        let listener = match &CONFIG.unix_socket {
+            Some(path) => {
+                let listener = server::bind_unix(path, CONFIG.unix_socket_mode)?;
+                info!("Server started at unix:{}", path.display());
+                Some(listener)
+            }
+            None => None,
+        };
+        let app = app.clone();
+        async move {
+            match listener {
+                Some(listener) => server::serve_unix(listener, app).await,
+                None => Ok(()),
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let unix = async { Ok(()) };
+
+    let tcp = async {
+        if !CONFIG.listen_tcp {
+            return Ok(());
+        }
+        let listener = server::bind(CONFIG.server_addr, CONFIG.ipv6_only)?;
+        match &CONFIG.tls {
+            Some(tls) => {
+                let tls = Arc::new(server::Tls::load(tls)?);
+
+                // 证书重新加载任务
+                //
+                // Certificate reload task
+                tokio::spawn(tls.clone().watch());
+
+                info!("Server started at https://{}", CONFIG.server_addr);
+                server::serve_tls(listener, app, tls).await?;
+            }
+            None => {
+                info!("Server started at {}", CONFIG.server_addr);
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    };
 
-    let listener = tokio::net::TcpListener::bind(&CONFIG.server_url).await?;
-    axum::serve(listener, app).await?;
+    tokio::try_join!(tcp, unix)?;
 
     Ok(())
 }
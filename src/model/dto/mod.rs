@@ -1,6 +1,8 @@
 use hashbrown::HashSet;
 use serde::Deserialize;
 
+use crate::common::label::Labels;
+
 #[derive(Deserialize, Debug)]
 pub struct AddAppDTO {
     pub app: String,
@@ -28,4 +30,288 @@ pub struct GetAppDTO {
     ///
     /// Specify the api under the app
     pub apis: Option<HashSet<String>>,
+    /// 是否返回滑动窗口计数
+    ///
+    /// Whether to return the sliding window counts
+    pub windows: Option<bool>,
+}
+
+/// 获取 api 的访问量
+///
+/// Get api access count
+#[derive(Deserialize, Debug)]
+pub struct GetApiDTO {
+    /// 是否返回滑动窗口计数
+    ///
+    /// Whether to return the sliding window counts
+    pub windows: Option<bool>,
+    /// 只统计包含这些标签的调用
+    ///
+    /// Only count the calls with these labels
+    pub labels: Option<Labels>,
+    /// 按这些标签的键分组
+    ///
+    /// Group by these label keys
+    pub group: Option<Vec<String>>,
+}
+
+/// 新增记录
+///
+/// Add record
+#[derive(Deserialize, Debug)]
+pub struct PostApiDTO {
+    /// 访客标识, 为空时由 ip 与 user agent 生成
+    ///
+    /// Visitor identifier, derived from the ip and user agent when empty
+    pub visitor: Option<String>,
+    /// 调用的标签, 如状态码, 版本, 地区
+    ///
+    /// Labels of the call, such as status, version or region
+    pub labels: Option<Labels>,
+    /// 调用的数值, 如耗时 (毫秒) 或数据大小
+    ///
+    /// Value of the call, such as latency (ms) or payload size
+    pub value: Option<f64>,
+}
+
+/// 导入数据中的一行
+///
+/// A line of imported data
+#[derive(Deserialize, Debug)]
+pub struct ImportDTO {
+    pub app: String,
+    /// 为空时仅创建 app
+    ///
+    /// Only the app is created when empty
+    pub api: Option<String>,
+    /// 为空时 count 表示总调用次数, 否则表示该秒的记录
+    ///
+    /// When empty, count is the total number of calls, otherwise the record of that second
+    pub time: Option<i64>,
+    pub count: Option<i64>,
+}
+
+/// 导入参数
+///
+/// Import parameters
+#[derive(Deserialize, Debug)]
+pub struct ImportQueryDTO {
+    /// 合并方式: add 或 replace
+    ///
+    /// Merge mode: add or replace
+    pub mode: Option<String>,
+}
+
+/// 订阅 app 的实时访问量
+///
+/// Subscribe to the live access count of the app
+#[derive(Deserialize, Debug)]
+pub struct StreamDTO {
+    /// 是否包含每秒速率
+    ///
+    /// Whether to include the per-second rates
+    pub rates: Option<bool>,
+}
+
+/// WebSocket 客户端消息
+///
+/// WebSocket client message
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum WsDTO {
+    /// 订阅 app, 指定 api 时只订阅该 api
+    ///
+    /// Subscribe to the app, only the api when specified
+    Subscribe { app: String, api: Option<String> },
+    /// 取消订阅 app, 指定 api 时只取消该 api
+    ///
+    /// Unsubscribe from the app, only the api when specified
+    Unsubscribe { app: String, api: Option<String> },
+    /// 记录一次 api 调用
+    ///
+    /// Record a call to the api
+    Incr { app: String, api: String },
+}
+
+/// 新增或修改告警规则
+///
+/// Add or update an alert rule
+#[derive(Deserialize, Debug)]
+pub struct AlertDTO {
+    pub app: String,
+    pub api: Option<String>,
+    /// above, idle 或 total
+    ///
+    /// above, idle or total
+    pub kind: String,
+    pub threshold: Option<i64>,
+    /// 窗口长度 (秒), 最长 24 小时
+    ///
+    /// Window length (seconds), 24 hours at most
+    pub window: Option<i64>,
+    pub webhook: Option<String>,
+}
+
+/// 设置配额
+///
+/// Set a quota
+#[derive(Deserialize, Debug)]
+pub struct QuotaDTO {
+    pub limit: i64,
+    /// day 或 month
+    ///
+    /// day or month
+    pub period: String,
+}
+
+/// 设置限流规则
+///
+/// Set a rate limit rule
+#[derive(Deserialize, Debug)]
+pub struct LimitDTO {
+    /// token_bucket 或 sliding_log
+    ///
+    /// token_bucket or sliding_log
+    pub algorithm: String,
+    pub capacity: i64,
+    /// 令牌桶每秒补充的令牌数
+    ///
+    /// Tokens refilled per second of the token bucket
+    pub refill: Option<f64>,
+    /// 滑动日志的窗口长度 (秒)
+    ///
+    /// Window length of the sliding log (seconds)
+    pub window: Option<i64>,
+}
+
+/// 获取不同访客的数量
+///
+/// Get the number of distinct visitors
+#[derive(Deserialize, Debug)]
+pub struct VisitorDTO {
+    /// 起始时间戳, 默认为 `to` 所在的日期
+    ///
+    /// Start timestamp, defaults to the day of `to`
+    pub from: Option<i64>,
+    /// 结束时间戳, 默认为当前时间
+    ///
+    /// End timestamp, defaults to now
+    pub to: Option<i64>,
+}
+
+/// 获取数值分布
+///
+/// Get the distribution of values
+#[derive(Deserialize, Debug)]
+pub struct HistogramDTO {
+    /// 起始时间戳, 默认为 `to` 所在的小时
+    ///
+    /// Start timestamp, defaults to the hour of `to`
+    pub from: Option<i64>,
+    /// 结束时间戳, 默认为当前时间
+    ///
+    /// End timestamp, defaults to now
+    pub to: Option<i64>,
+}
+
+/// 设置仪表的值
+///
+/// Set the value of a gauge
+#[derive(Deserialize, Debug)]
+pub struct GaugeDTO {
+    pub value: f64,
+}
+
+/// 增加或减少仪表的值
+///
+/// Increase or decrease the value of a gauge
+#[derive(Deserialize, Debug)]
+pub struct GaugeStepDTO {
+    pub by: Option<f64>,
+}
+
+/// 获取仪表的汇总
+///
+/// Get the rollup of a gauge
+#[derive(Deserialize, Debug)]
+pub struct GaugeQueryDTO {
+    /// 起始时间戳, 默认为 `to` 之前一小时
+    ///
+    /// Start timestamp, defaults to an hour before `to`
+    pub from: Option<i64>,
+    /// 结束时间戳, 默认为当前时间
+    ///
+    /// End timestamp, defaults to now
+    pub to: Option<i64>,
+}
+
+/// 累加或设置计数
+///
+/// Add to or set a counter
+#[derive(Deserialize, Debug)]
+pub struct CountDTO {
+    pub data: i64,
+}
+
+/// 设置键值存储中的值
+///
+/// Set a value in the key-value store
+#[derive(Deserialize, Debug)]
+pub struct KvDTO {
+    pub data: serde_json::Value,
+    /// 过期时间 (秒), 为空时不过期
+    ///
+    /// Time to live (seconds), never expires when empty
+    pub ttl: Option<i64>,
+    /// 只有当前版本相同才写入, 0 表示 key 不存在
+    ///
+    /// Only written when the current version matches, 0 means the key does not exist
+    pub version: Option<i64>,
+}
+
+/// 设置功能开关
+///
+/// Set a feature flag
+#[derive(Deserialize, Debug)]
+pub struct FlagDTO {
+    /// 所属的 app, 曝光次数记录在其中的同名 api
+    ///
+    /// The app it belongs to, exposures are recorded in the api of the same name
+    pub app: String,
+    /// 开启的用户比例 (0 - 100)
+    ///
+    /// Percentage of users the flag is on for (0 - 100)
+    #[serde(default)]
+    pub percentage: f64,
+    #[serde(default)]
+    pub allow: HashSet<String>,
+    #[serde(default)]
+    pub deny: HashSet<String>,
+    /// 同时设置布尔值存储中的总开关
+    ///
+    /// Also set the master switch in the bool store
+    pub enabled: Option<bool>,
+}
+
+/// 对用户求值功能开关
+///
+/// Evaluate a feature flag for a user
+#[derive(Deserialize, Debug)]
+pub struct FlagEvalDTO {
+    pub user: String,
+}
+
+/// 等待值改动
+///
+/// Wait for the value to change
+#[derive(Deserialize, Debug)]
+pub struct WatchDTO {
+    /// 已知的版本号, 当前版本号与之不同时返回, 为空时立即返回
+    ///
+    /// The known version, returns once the current version differs from it, immediately when empty
+    pub version: Option<u64>,
+    /// 最长等待的秒数
+    ///
+    /// Maximum seconds to wait
+    pub timeout: Option<u64>,
 }
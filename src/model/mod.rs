@@ -1,3 +1,5 @@
+use serde::Serialize;
+
 pub mod dto;
 pub mod vo;
 
@@ -11,3 +13,196 @@ pub struct Api {
     pub api: String,
     pub count: i64,
 }
+
+/// 告警规则
+///
+/// Alert rule
+#[derive(sqlx::FromRow, Serialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub id: i64,
+    pub app: String,
+    /// 为空时对整个 app 生效
+    ///
+    /// Applies to the whole app when empty
+    pub api: Option<String>,
+    /// above: 窗口内调用次数大于阈值, idle: 窗口内没有调用, total: 总调用次数达到阈值
+    ///
+    /// above: calls in the window exceed the threshold, idle: no calls in the window, total: the total reaches the threshold
+    pub kind: String,
+    pub threshold: i64,
+    /// 窗口长度 (秒)
+    ///
+    /// Window length (seconds)
+    pub window: i64,
+    /// 为空时使用配置中的默认回调地址
+    ///
+    /// The default webhook in the configuration is used when empty
+    pub webhook: Option<String>,
+    pub firing: bool,
+}
+
+/// 配额, 空 api 表示整个 app 的配额
+///
+/// Quota, an empty api is the quota of the whole app
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Quota {
+    pub app: String,
+    pub api: String,
+    pub limit: i64,
+    /// day 或 month
+    ///
+    /// day or month
+    pub period: String,
+}
+
+/// 限流规则
+///
+/// Rate limit rule
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct Limit {
+    pub app: String,
+    pub api: String,
+    /// token_bucket 或 sliding_log
+    ///
+    /// token_bucket or sliding_log
+    pub algorithm: String,
+    pub capacity: i64,
+    /// 令牌桶每秒补充的令牌数
+    ///
+    /// Tokens refilled per second of the token bucket
+    pub refill: f64,
+    /// 滑动日志的窗口长度 (秒)
+    ///
+    /// Window length of the sliding log (seconds)
+    pub window: i64,
+}
+
+/// 某个 api 某天的访客草图
+///
+/// Visitor sketch of an api for a day
+#[derive(sqlx::FromRow, Debug)]
+pub struct Visitor {
+    pub app: String,
+    pub api: String,
+    /// 当天 0 点的时间戳 (UTC)
+    ///
+    /// Timestamp of the midnight of the day (UTC)
+    pub day: i64,
+    pub sketch: Vec<u8>,
+}
+
+/// 某个 api 的一个标签组合的调用次数
+///
+/// Number of calls of a label combination of an api
+#[derive(sqlx::FromRow, Debug)]
+pub struct Label {
+    pub app: String,
+    pub api: String,
+    /// 按键排序的标签 JSON
+    ///
+    /// Label JSON sorted by key
+    pub labels: String,
+    pub count: i64,
+}
+
+/// 某个 api 某小时的直方图
+///
+/// Histogram of an api for an hour
+#[derive(sqlx::FromRow, Debug)]
+pub struct Histogram {
+    pub app: String,
+    pub api: String,
+    /// 该小时开始的时间戳
+    ///
+    /// Timestamp of the start of the hour
+    pub time: i64,
+    /// 直方图 JSON
+    ///
+    /// Histogram JSON
+    pub data: String,
+}
+
+/// 仪表的当前值
+///
+/// Current value of a gauge
+#[derive(sqlx::FromRow, Debug)]
+pub struct Gauge {
+    pub app: String,
+    pub api: String,
+    pub value: f64,
+}
+
+/// 仪表一分钟内的采样统计
+///
+/// Sample statistics of a gauge for a minute
+#[derive(sqlx::FromRow, Debug)]
+pub struct GaugeSample {
+    pub app: String,
+    pub api: String,
+    /// 该分钟开始的时间戳
+    ///
+    /// Timestamp of the start of the minute
+    pub time: i64,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    pub count: i64,
+    pub last: f64,
+}
+
+/// 以任意 key 记录的计数
+///
+/// Counter keyed by an arbitrary key
+#[derive(sqlx::FromRow, Debug)]
+pub struct Count {
+    pub key: String,
+    pub count: i64,
+}
+
+/// 键值存储中的值
+///
+/// A value in the key-value store
+#[derive(sqlx::FromRow, Debug)]
+pub struct Kv {
+    /// bool, string 或 json
+    ///
+    /// bool, string or json
+    pub kind: String,
+    pub key: String,
+    /// 值的 JSON
+    ///
+    /// JSON of the value
+    pub value: String,
+    pub version: i64,
+    pub expires: Option<i64>,
+}
+
+/// 功能开关的规则
+///
+/// Rules of a feature flag
+#[derive(sqlx::FromRow, Debug)]
+pub struct Flag {
+    pub key: String,
+    pub app: String,
+    pub percentage: f64,
+    /// 允许名单 JSON
+    ///
+    /// Allow list JSON
+    pub allow: String,
+    /// 禁止名单 JSON
+    ///
+    /// Deny list JSON
+    pub deny: String,
+}
+
+/// 幂等键与原来的调用次数
+///
+/// Idempotency key and the original count
+#[derive(sqlx::FromRow, Debug)]
+pub struct Idempotency {
+    pub app: String,
+    pub api: String,
+    pub key: String,
+    pub count: i64,
+    pub expires: i64,
+}
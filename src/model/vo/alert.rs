@@ -0,0 +1,24 @@
+use serde::Serialize;
+
+use crate::model::Alert;
+
+/// 告警回调内容
+///
+/// Alert webhook payload
+#[derive(Debug, Serialize)]
+pub struct AlertEventVO {
+    /// 同一次状态变化的重试使用相同的 id, 便于接收方去重
+    ///
+    /// Retries of the same state change share the id so receivers can deduplicate
+    pub id: String,
+    /// firing 或 resolved
+    ///
+    /// firing or resolved
+    pub state: &'static str,
+    pub alert: Alert,
+    /// 触发检查时的值
+    ///
+    /// Value at the time of the check
+    pub value: i64,
+    pub time: i64,
+}
@@ -0,0 +1,22 @@
+use serde::Serialize;
+
+use super::{label::LabelGroupVO, window::WindowVO};
+
+/// 未要求额外数据时只返回调用次数
+///
+/// Only the count is returned when no extra data is requested
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum GetApiVO {
+    Count(i64),
+    Detail {
+        /// 指定标签时为符合标签的调用次数
+        ///
+        /// The number of calls matching the labels when labels are given
+        count: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        windows: Option<WindowVO>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        groups: Option<Vec<LabelGroupVO>>,
+    },
+}
@@ -1,9 +1,13 @@
 use serde::Serialize;
 
+use super::window::WindowVO;
+
 #[derive(Debug, Serialize)]
 pub struct GetAppVO {
     pub total: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub windows: Option<WindowVO>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub apis: Option<Vec<ApiCount>>,
 }
 
@@ -11,4 +15,6 @@ pub struct GetAppVO {
 pub struct ApiCount {
     pub api: String,
     pub count: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub windows: Option<WindowVO>,
 }
@@ -0,0 +1,13 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct ReloadVO {
+    /// 已生效的配置项
+    ///
+    /// Keys that took effect
+    pub applied: Vec<String>,
+    /// 已改动但需要重启才能生效的配置项
+    ///
+    /// Keys that changed but need a restart to take effect
+    pub restart: Vec<String>,
+}
@@ -0,0 +1,27 @@
+use serde::Serialize;
+
+/// 功能开关
+///
+/// Feature flag
+#[derive(Debug, Serialize)]
+pub struct FlagVO {
+    pub key: String,
+    pub app: String,
+    pub percentage: f64,
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    /// 布尔值存储中同名的总开关, 不存在时为 false
+    ///
+    /// The master switch of the same key in the bool store, false when missing
+    pub enabled: bool,
+}
+
+/// 功能开关对用户的求值结果
+///
+/// Evaluation of a feature flag for a user
+#[derive(Debug, Serialize)]
+pub struct FlagEvalVO {
+    pub key: String,
+    pub user: String,
+    pub enabled: bool,
+}
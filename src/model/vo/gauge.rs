@@ -0,0 +1,36 @@
+use serde::Serialize;
+
+/// 一分钟内的采样统计
+///
+/// Sample statistics of a minute
+#[derive(Debug, Serialize)]
+pub struct GaugeBucketVO {
+    pub time: i64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub last: f64,
+}
+
+/// 仪表的当前值与一段时间内的汇总
+///
+/// Current value of a gauge and its rollup over a range
+#[derive(Debug, Serialize)]
+pub struct GaugeVO {
+    pub value: f64,
+    /// 起始分钟
+    ///
+    /// First minute
+    pub from: i64,
+    /// 结束分钟
+    ///
+    /// Last minute
+    pub to: i64,
+    /// 范围内没有采样时为空
+    ///
+    /// Empty when there are no samples in the range
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+    pub history: Vec<GaugeBucketVO>,
+}
@@ -0,0 +1,63 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct ReadyVO {
+    /// 数据库是否可以访问
+    ///
+    /// Whether the database is reachable
+    pub database: bool,
+    /// 最近是否同步过
+    ///
+    /// Whether a sync completed recently
+    pub synced: bool,
+    /// 上次同步完成的时间, 启动后尚未同步时为空
+    ///
+    /// Time of the last completed sync, empty before the first sync after startup
+    pub last_sync: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusVO {
+    pub version: String,
+    /// 运行的秒数
+    ///
+    /// Seconds since startup
+    pub uptime: i64,
+    /// app 数量
+    ///
+    /// Number of apps
+    pub apps: usize,
+    /// api 数量
+    ///
+    /// Number of apis
+    pub apis: usize,
+    /// 等待写入数据库的数据量
+    ///
+    /// Amount of data waiting to be written to the database
+    pub pending: PendingVO,
+    /// 数据库文件的字节数
+    ///
+    /// Size of the database file in bytes
+    pub db_size: u64,
+    /// 上次同步完成的时间
+    ///
+    /// Time of the last completed sync
+    pub last_sync: Option<i64>,
+    /// 上次同步的耗时 (毫秒)
+    ///
+    /// Duration of the last sync (milliseconds)
+    pub last_sync_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PendingVO {
+    pub apps: usize,
+    pub apis: usize,
+    /// 调用记录, 每个 api 每秒为一条
+    ///
+    /// Call records, one per api per second
+    pub records: usize,
+    pub counts: usize,
+    pub kv: usize,
+    pub idempotency: usize,
+}
@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+/// 一段时间内的数值分布
+///
+/// Distribution of values over a range
+#[derive(Debug, Serialize)]
+pub struct HistogramVO {
+    /// 起始小时
+    ///
+    /// First hour
+    pub from: i64,
+    /// 结束小时
+    ///
+    /// Last hour
+    pub to: i64,
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
@@ -0,0 +1,21 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Default)]
+pub struct ImportVO {
+    /// 新建的 app 数量
+    ///
+    /// Number of apps created
+    pub apps: usize,
+    /// 新建的 api 数量
+    ///
+    /// Number of apis created
+    pub apis: usize,
+    /// 合并的总调用次数数量
+    ///
+    /// Number of totals merged
+    pub totals: usize,
+    /// 合并的记录数量
+    ///
+    /// Number of records merged
+    pub records: usize,
+}
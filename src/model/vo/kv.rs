@@ -0,0 +1,18 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// 键值存储中的值
+///
+/// A value in the key-value store
+#[derive(Debug, Serialize)]
+pub struct KvVO {
+    pub data: Value,
+    /// 每次写入加 1, 用于比较并设置
+    ///
+    /// Increased on every write, used for compare-and-set
+    pub version: i64,
+    /// 过期时间戳, 为空时不过期
+    ///
+    /// Expiry timestamp, never expires when empty
+    pub expires: Option<i64>,
+}
@@ -0,0 +1,12 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// 按标签分组的调用次数
+///
+/// Number of calls grouped by labels
+#[derive(Debug, Serialize)]
+pub struct LabelGroupVO {
+    pub labels: BTreeMap<String, String>,
+    pub count: i64,
+}
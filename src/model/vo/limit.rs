@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+/// 限流检查结果
+///
+/// Rate limit check result
+#[derive(Debug, Serialize)]
+pub struct LimitVO {
+    pub allowed: bool,
+    /// 剩余额度
+    ///
+    /// Remaining units
+    pub remaining: i64,
+    /// 需要等待的秒数
+    ///
+    /// Seconds to wait before retrying
+    pub retry_after: f64,
+}
@@ -1 +1,18 @@
+pub mod alert;
+pub mod api;
 pub mod app;
+pub mod config;
+pub mod flag;
+pub mod gauge;
+pub mod health;
+pub mod histogram;
+pub mod import;
+pub mod kv;
+pub mod label;
+pub mod limit;
+pub mod quota;
+pub mod stream;
+pub mod visitor;
+pub mod watch;
+pub mod window;
+pub mod ws;
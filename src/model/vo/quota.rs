@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+/// 配额及其用量
+///
+/// A quota and its usage
+#[derive(Debug, Serialize, Clone)]
+pub struct QuotaVO {
+    pub app: String,
+    /// 为空时为整个 app 的配额
+    ///
+    /// The quota of the whole app when empty
+    pub api: Option<String>,
+    pub limit: i64,
+    pub period: &'static str,
+    /// 当前周期内已用的次数
+    ///
+    /// Calls used in the current period
+    pub used: i64,
+    /// 重置时间
+    ///
+    /// Reset time
+    pub reset: i64,
+}
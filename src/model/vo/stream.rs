@@ -0,0 +1,22 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct StreamVO {
+    pub total: i64,
+    pub apis: Vec<ApiDelta>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ApiDelta {
+    pub api: String,
+    pub count: i64,
+    /// 距上次推送的增量
+    ///
+    /// Increase since the last push
+    pub delta: i64,
+    /// 上一秒的调用次数
+    ///
+    /// Number of calls in the last second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<i64>,
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+/// 不同访客的数量
+///
+/// Number of distinct visitors
+#[derive(Debug, Serialize)]
+pub struct VisitorVO {
+    /// 起始日期 (UTC 0 点的时间戳)
+    ///
+    /// First day (timestamp of UTC midnight)
+    pub from: i64,
+    /// 结束日期 (UTC 0 点的时间戳)
+    ///
+    /// Last day (timestamp of UTC midnight)
+    pub to: i64,
+    /// 估算的不同访客数量
+    ///
+    /// Estimated number of distinct visitors
+    pub visitors: i64,
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+/// 监听的结果
+///
+/// Result of a watch
+#[derive(Debug, Serialize)]
+pub struct WatchVO<T> {
+    /// 当前的版本号, 用于下次监听
+    ///
+    /// Current version, used by the next watch
+    pub version: u64,
+    /// 是否在超时前改动
+    ///
+    /// Whether it changed before the timeout
+    pub changed: bool,
+    /// 当前的值, 不存在时为空
+    ///
+    /// Current value, empty when it does not exist
+    pub data: Option<T>,
+}
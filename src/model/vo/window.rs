@@ -0,0 +1,36 @@
+use std::ops::Add;
+
+use serde::Serialize;
+
+/// 滑动窗口计数
+///
+/// Sliding window counts
+#[derive(Debug, Serialize, Default, Clone, Copy)]
+pub struct WindowVO {
+    #[serde(rename = "1m")]
+    pub m1: i64,
+    #[serde(rename = "5m")]
+    pub m5: i64,
+    #[serde(rename = "1h")]
+    pub h1: i64,
+    #[serde(rename = "24h")]
+    pub d1: i64,
+    /// 每秒请求数
+    ///
+    /// Requests per second
+    pub rps: f64,
+}
+
+impl Add for WindowVO {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        WindowVO {
+            m1: self.m1 + other.m1,
+            m5: self.m5 + other.m5,
+            h1: self.h1 + other.h1,
+            d1: self.d1 + other.d1,
+            rps: self.rps + other.rps,
+        }
+    }
+}
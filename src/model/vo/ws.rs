@@ -0,0 +1,40 @@
+use serde::Serialize;
+
+use super::stream::ApiDelta;
+
+/// WebSocket 服务端消息
+///
+/// WebSocket server message
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum WsVO {
+    /// 订阅的 api 发生变化
+    ///
+    /// Subscribed apis changed
+    Update {
+        app: String,
+        total: i64,
+        apis: Vec<ApiDelta>,
+    },
+    /// 调用记录结果
+    ///
+    /// Result of recording a call
+    Incr {
+        app: String,
+        api: String,
+        count: i64,
+    },
+    Error {
+        code: i64,
+        msg: String,
+    },
+}
+
+impl From<(i64, &str)> for WsVO {
+    fn from(typ: (i64, &str)) -> Self {
+        WsVO::Error {
+            code: typ.0,
+            msg: typ.1.to_owned(),
+        }
+    }
+}
@@ -49,4 +49,12 @@ where
             data: None,
         }
     }
+
+    pub fn fail_with(typ: (i64, &str), data: T) -> Self {
+        Resp {
+            code: typ.0,
+            msg: Some(typ.1.to_owned()),
+            data: Some(data),
+        }
+    }
 }
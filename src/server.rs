@@ -0,0 +1,251 @@
+use std::{
+    convert::Infallible,
+    fs::File,
+    io::BufReader,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::{response::Response, BoxError, Router};
+use hyper::{body::Incoming, Request};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto::Builder,
+    service::TowerToHyperService,
+};
+use parking_lot::{Mutex, RwLock};
+use socket2::{Domain, Socket, Type};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+};
+use tokio_rustls::{
+    rustls::{crypto::ring, version, ServerConfig, SupportedProtocolVersion},
+    TlsAcceptor,
+};
+use tower_service::Service;
+use tracing::{debug, info, warn};
+
+use crate::config::TlsConfig;
+
+/// 等待接受的连接数上限
+///
+/// Maximum number of connections waiting to be accepted
+const BACKLOG: i32 = 1024;
+
+/// TLS 握手的超时时间, 避免不完成握手的连接一直占用资源
+///
+/// Timeout of the TLS handshake, so connections that never finish it do not hold resources
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 接受连接失败后的等待时间, 避免文件描述符耗尽时空转并刷屏日志, 与 `axum::serve` 相同
+///
+/// Delay after failing to accept a connection, so running out of file descriptors does not spin
+/// and flood the log, the same as `axum::serve`
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+/// 监听 TCP 地址. 监听 IPv6 地址时, `ipv6_only` 为 false 则同时接受 IPv4 连接,
+/// 不依赖系统的默认设置
+///
+/// Listen on a TCP address. On an IPv6 address, IPv4 connections are also accepted
+/// unless `ipv6_only`, regardless of the system default
+pub fn bind(addr: SocketAddr, ipv6_only: bool) -> Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(ipv6_only)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket
+        .bind(&addr.into())
+        .with_context(|| format!("failed to bind {}", addr))?;
+    socket.listen(BACKLOG)?;
+    Ok(TcpListener::from_std(socket.into())?)
+}
+
+/// 检查证书文件是否改动的间隔
+///
+/// Interval to check whether the certificate files changed
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 可以重新加载证书的 TLS 设置
+///
+/// TLS settings whose certificates can be reloaded
+pub struct Tls {
+    config: RwLock<Arc<ServerConfig>>,
+    settings: TlsConfig,
+    /// 证书与私钥文件上次加载时的修改时间
+    ///
+    /// Modification times of the certificate and key files at the last load
+    modified: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl Tls {
+    pub fn load(settings: &TlsConfig) -> Result<Self> {
+        let modified = Self::modified(settings);
+        Ok(Self {
+            config: RwLock::new(Arc::new(Self::build(settings)?)),
+            settings: settings.clone(),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    fn modified(settings: &TlsConfig) -> (Option<SystemTime>, Option<SystemTime>) {
+        let modified = |path| {
+            std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        };
+        (modified(&settings.cert), modified(&settings.key))
+    }
+
+    fn build(settings: &TlsConfig) -> Result<ServerConfig> {
+        let open = |path: &PathBuf| {
+            File::open(path)
+                .map(BufReader::new)
+                .with_context(|| format!("failed to open {}", path.display()))
+        };
+        let certs = rustls_pemfile::certs(&mut open(&settings.cert)?)
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("failed to read {}", settings.cert.display()))?;
+        if certs.is_empty() {
+            bail!("no certificate found in {}", settings.cert.display());
+        }
+        let key = rustls_pemfile::private_key(&mut open(&settings.key)?)
+            .with_context(|| format!("failed to read {}", settings.key.display()))?
+            .ok_or_else(|| anyhow!("no private key found in {}", settings.key.display()))?;
+        let versions: &[&SupportedProtocolVersion] = match settings.tls13_only {
+            true => &[&version::TLS13],
+            false => &[&version::TLS13, &version::TLS12],
+        };
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_protocol_versions(versions)?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.config.read().clone())
+    }
+
+    /// 定时检查证书文件, 改动时重新加载, 加载失败时继续使用原来的证书
+    ///
+    /// Check the certificate files periodically and reload them when changed,
+    /// the current certificates are kept when loading fails
+    pub async fn watch(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            let modified = Self::modified(&self.settings);
+            if *self.modified.lock() == modified {
+                continue;
+            }
+            *self.modified.lock() = modified;
+            match Self::build(&self.settings) {
+                Ok(config) => {
+                    *self.config.write() = Arc::new(config);
+                    info!("TLS certificates reloaded");
+                }
+                Err(e) => warn!("Failed to reload TLS certificates: {:#}", e),
+            }
+        }
+    }
+}
+
+/// 提供 HTTPS
+///
+/// Serve HTTPS
+pub async fn serve_tls(listener: TcpListener, app: Router, tls: Arc<Tls>) -> Result<()> {
+    let mut make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                continue;
+            }
+        };
+        let acceptor = tls.acceptor();
+        let service = make_service.call(addr).await.unwrap();
+        tokio::spawn(async move {
+            let handshake = tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream));
+            let stream = match handshake.await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    debug!("TLS handshake with {} failed: {}", addr, e);
+                    return;
+                }
+                Err(_) => {
+                    debug!("TLS handshake with {} timed out", addr);
+                    return;
+                }
+            };
+            if let Err(e) = serve_connection(stream, service).await {
+                debug!("Connection from {} closed: {}", addr, e);
+            }
+        });
+    }
+}
+
+/// 监听 Unix 套接字, 移除遗留的套接字文件, 并设置文件权限
+///
+/// Listen on a Unix socket, removing a stale socket file and setting the file permissions
+#[cfg(unix)]
+pub fn bind_unix(path: &std::path::Path, mode: u32) -> Result<UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            bail!("{} exists and is not a socket", path.display());
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener =
+        UnixListener::bind(path).with_context(|| format!("failed to bind {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+/// 在 Unix 套接字上提供 HTTP, 调用者的访问由套接字文件的权限控制
+///
+/// Serve HTTP on a Unix socket, access is controlled by the permissions of the socket file
+#[cfg(unix)]
+pub async fn serve_unix(listener: UnixListener, app: Router) -> Result<()> {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                continue;
+            }
+        };
+        let service = app.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_connection(stream, service).await {
+                debug!("Unix socket connection closed: {}", e);
+            }
+        });
+    }
+}
+
+/// 在一个连接上提供 HTTP/1 与 HTTP/2, 支持 WebSocket 升级
+///
+/// Serve HTTP/1 and HTTP/2 on a connection, with WebSocket upgrades
+async fn serve_connection<I, S>(io: I, service: S) -> Result<(), BoxError>
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Service<Request<Incoming>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    Builder::new(TokioExecutor::new())
+        .serve_connection_with_upgrades(TokioIo::new(io), TowerToHyperService::new(service))
+        .await
+}
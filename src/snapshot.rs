@@ -0,0 +1,107 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use tracing::{error, info};
+
+use crate::{config, pool, sync::flush};
+
+/// 生成数据库快照
+///
+/// 先将等待中的数据写入数据库, 再通过 `VACUUM INTO` 生成一致的副本.
+/// 未指定路径时写入快照目录并按配置轮换. 只能写入本进程等待中的数据,
+/// 因此命令行生成的快照不包含运行中的服务尚未写入的数据.
+///
+/// Make a database snapshot
+///
+/// Pending data is flushed first, then a consistent copy is made with `VACUUM INTO`.
+/// Without a path the snapshot is written to the snapshot directory and rotated. Only the pending
+/// data of this process can be flushed, so a snapshot made from the command line does not include
+/// what a running server has not written yet.
+pub async fn snapshot(path: Option<PathBuf>) -> Result<PathBuf> {
+    let config = config::current();
+    let rotate_dir = path.is_none().then(|| config.snapshot_dir.clone());
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let time = std::time::SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_micros();
+            config.snapshot_dir.join(format!("db-{}.sqlite", time))
+        }
+    };
+    if path.exists() {
+        bail!("Snapshot file already exists: {}", path.display());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    flush().await;
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().as_ref())
+        .execute(pool!())
+        .await?;
+
+    info!("Snapshot created: {}", path.display());
+
+    if let Some(dir) = rotate_dir {
+        rotate(&dir, config.snapshot_keep)?;
+    }
+
+    Ok(path)
+}
+
+/// 只保留最新的若干个快照
+///
+/// Keep only the newest snapshots
+fn rotate(dir: &Path, keep: usize) -> Result<()> {
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("db-") && name.ends_with(".sqlite"))
+        })
+        .collect();
+
+    // 文件名中的微秒时间戳长度相同, 按名称排序即按时间排序
+    //
+    // Microsecond timestamps in the file names have the same length, so sorting by name sorts by time
+    snapshots.sort();
+
+    let remove = snapshots.len().saturating_sub(keep);
+    for path in snapshots.into_iter().take(remove) {
+        info!("Remove old snapshot: {}", path.display());
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// 定时快照
+///
+/// Scheduled snapshots
+pub async fn snapshot_task() {
+    info!("Snapshot task started");
+    loop {
+        // 关闭时每秒检查一次, 以便重新加载配置后开启
+        //
+        // Check every second while disabled, so it can be enabled by a reload
+        let interval = config::current().snapshot_interval;
+        if interval == 0 {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        if config::current().snapshot_interval == 0 {
+            continue;
+        }
+        if let Err(e) = snapshot(None).await {
+            error!("Snapshot failed: {}", e);
+        }
+    }
+}
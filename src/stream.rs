@@ -0,0 +1,86 @@
+use std::{sync::Arc, time::Duration};
+
+use hashbrown::HashMap;
+use tracing::info;
+
+use crate::{
+    common::hub::StreamEvent,
+    config, context,
+    model::vo::stream::{ApiDelta, StreamVO},
+};
+
+/// 生成 app 的推送数据
+///
+/// `prev` 为上次推送时各 api 的调用次数, 此时只包含发生变化的 api,
+/// 为空时包含所有 api
+///
+/// Make the push data of the app
+///
+/// `prev` holds the counts of each api at the last push, in which case only changed apis are included,
+/// all apis are included when it is empty
+pub fn make_vo(app: &str, prev: Option<&HashMap<String, i64>>) -> (StreamVO, HashMap<String, i64>) {
+    let apis = context!().apis.get_apis(app);
+    let rates = context!().wait_record.get_rate(app);
+    let total = apis.values().sum();
+
+    let mut deltas: Vec<ApiDelta> = apis
+        .iter()
+        .filter_map(|(api, count)| {
+            let delta = match prev {
+                Some(prev) => count - prev.get(api).copied().unwrap_or_default(),
+                None => 0,
+            };
+            let rate = rates.get(api).copied().unwrap_or_default();
+            (prev.is_none() || delta != 0 || rate != 0).then(|| ApiDelta {
+                api: api.to_owned(),
+                count: *count,
+                delta,
+                rate: Some(rate),
+            })
+        })
+        .collect();
+    deltas.sort_by(|a, b| a.api.cmp(&b.api));
+
+    (
+        StreamVO {
+            total,
+            apis: deltas,
+        },
+        apis,
+    )
+}
+
+/// 实时推送任务, 每个间隔为所有被订阅的 app 生成一次事件
+///
+/// Live push task, makes one event per interval for every subscribed app
+pub async fn stream_task() {
+    info!("Stream task started");
+    let mut tick = config::current().stream_tick;
+    let mut interval = tokio::time::interval(Duration::from_millis(tick));
+    loop {
+        interval.tick().await;
+
+        // 重新加载配置后使用新的间隔
+        //
+        // Use the new interval after a reload
+        if tick != config::current().stream_tick {
+            tick = config::current().stream_tick;
+            interval = tokio::time::interval(Duration::from_millis(tick));
+        }
+
+        for (app, channel) in context!().hub.get_apps() {
+            let mut channel = channel.lock();
+            let (vo, counts) = make_vo(&app, Some(&channel.prev));
+            let changed = !vo.apis.is_empty();
+
+            // 上次有变化时需要再推送一次, 以让速率归零
+            //
+            // Push once more after a change to let the rates drop to zero
+            if changed || channel.changed {
+                let _ = channel.sender.send(Arc::new(StreamEvent::new(vo)));
+            }
+            channel.prev = counts;
+            channel.changed = changed;
+        }
+    }
+}
@@ -1,96 +1,253 @@
 use hashbrown::HashMap;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use parking_lot::const_mutex;
+use tokio::sync::{Mutex, MutexGuard};
 use tracing::info;
 
 use crate::{
-    config::CONFIG,
+    common::{kv::Kind, watch::Target},
+    config::{self, CONFIG},
     context,
-    db::{add_rec, make_api_table, make_app_table, update_count},
+    db::{
+        add_gauge_sample, add_rec, delete_idempotency, delete_kv, make_api_table, make_app_table,
+        set_count, set_gauge, set_histogram, set_idempotency, set_kv, set_label, set_salt,
+        set_visitor, update_count,
+    },
+    model::{GaugeSample, Kv},
+    util,
 };
 
+/// 同步锁, 保证同一时间只有一个同步任务在写入数据库
+///
+/// Sync lock, ensures only one sync writes to the database at a time
+static SYNC_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// 获取同步锁, 持有期间不会有数据从内存移入数据库, 用于同时读取两者
+///
+/// Take the sync lock, no data moves from memory to the database while it is held,
+/// used to read both together
+pub async fn lock() -> MutexGuard<'static, ()> {
+    SYNC_LOCK.lock().await
+}
+
+/// 上次同步完成的时间与耗时
+///
+/// Time and duration of the last completed sync
+static LAST_SYNC: parking_lot::Mutex<Option<(i64, Duration)>> = const_mutex(None);
+
+/// 获取上次同步完成的时间与耗时, 启动后尚未同步时为空
+///
+/// Get the time and duration of the last completed sync, empty before the first sync after startup
+pub fn last_sync() -> Option<(i64, Duration)> {
+    *LAST_SYNC.lock()
+}
+
 /// 数据库同步
 ///
 /// Database sync
 pub async fn db_sync() {
     info!("Database sync task started");
     loop {
-        tokio::time::sleep(Duration::from_secs(CONFIG.sync_interval)).await;
+        tokio::time::sleep(Duration::from_secs(config::current().sync_interval)).await;
+        flush().await;
+
+        // 清理限流器中空闲的 key
+        //
+        // Clean up idle keys of the rate limiters
+        context!().limits.evict(util::now_f64());
 
-        // 获取需要新增的 app
+        // 移除过期的值, 在下次同步时从数据库删除
         //
-        // Get new app
-        let wait_app = context!().wait_app.get_all();
+        // Remove expired values, they are deleted from the database by the next sync
+        for (kind, key) in context!().kv.expire(util::now()) {
+            context!().watches.notify(Target::Kv(kind, &key));
+        }
+
+        // 删除数据库中过期的幂等键
+        //
+        // Delete the expired idempotency keys in the database
+        if CONFIG.idempotency_persist {
+            delete_idempotency(util::now()).await;
+        }
 
-        // 添加 app, 建立相关表
+        // 清理没有监听者的值
         //
-        // Add app, build related tables
-        if !wait_app.is_empty() {
-            info!("wait_app: {:?}", wait_app);
-            for app in wait_app.iter() {
-                make_app_table(app).await;
+        // Clean up the values without watchers
+        context!().watches.evict();
+    }
+}
+
+/// 将所有等待中的数据写入数据库
+///
+/// Write all pending data to the database
+pub async fn flush() {
+    let _lock = SYNC_LOCK.lock().await;
+    let started = Instant::now();
+
+    // 获取需要新增的 app
+    //
+    // Get new app
+    let wait_app = context!().wait_app.get_all();
+
+    // 添加 app, 建立相关表
+    //
+    // Add app, build related tables
+    if !wait_app.is_empty() {
+        info!("wait_app: {:?}", wait_app);
+        for app in wait_app.iter() {
+            make_app_table(app).await;
+        }
+    }
+
+    // 获取需要新增的api
+    //
+    // Get new api
+    let wait_api = context!().wait_api.get_apis();
+
+    // 添加 api, 建立相关表
+    //
+    // Add api, build related tables
+    if !wait_api.is_empty() {
+        info!("wait_api: {:?}", wait_api);
+        for (app, apis) in wait_api.iter() {
+            for api in apis.iter() {
+                make_api_table(app, api).await;
             }
         }
+    }
 
-        // 获取需要新增的api
+    // 获取需要新增的记录
+    //
+    // Get new record
+    let wait_record = context!().wait_record.get_records();
+    if !wait_record.is_empty() {
+        info!("wait_record: {:?}", wait_record);
+
+        // 需要更新Api的值
         //
-        // Get new api
-        let wait_api = context!().wait_api.get_apis();
+        // Api value to be updated
+        let api_update: HashMap<&String, HashMap<&String, i64>> = wait_record
+            .iter()
+            .map(|(app, apis)| {
+                let apis: HashMap<&String, i64> = apis
+                    .iter()
+                    .map(|(api, _)| (api, context!().apis.get_api(app, api)))
+                    .collect();
+                (app, apis)
+            })
+            .collect();
+
+        info!("api_update: {:?}", api_update);
 
-        // 添加 api, 建立相关表
+        // 更新api表中的记录
         //
-        // Add api, build related tables
-        if !wait_api.is_empty() {
-            info!("wait_api: {:?}", wait_api);
-            for (app, apis) in wait_api.iter() {
-                for api in apis.iter() {
-                    make_api_table(app, api).await;
-                }
+        // Update the record in the api table
+        for (app, apis) in api_update.iter() {
+            for (api, count) in apis.iter() {
+                update_count(app, api, count).await;
             }
         }
 
-        // 获取需要新增的记录
+        // 添加记录
         //
-        // Get new record
-        let wait_record = context!().wait_record.get_records();
-        if !wait_record.is_empty() {
-            info!("wait_record: {:?}", wait_record);
-
-            // 需要更新Api的值
-            //
-            // Api value to be updated
-            let api_update: HashMap<&String, HashMap<&String, i64>> = wait_record
-                .iter()
-                .map(|(app, apis)| {
-                    let apis: HashMap<&String, i64> = apis
-                        .iter()
-                        .map(|(api, _)| (api, context!().apis.get_api(app, api)))
-                        .collect();
-                    (app, apis)
-                })
-                .collect();
-
-            info!("api_update: {:?}", api_update);
-
-            // 更新api表中的记录
-            //
-            // Update the record in the api table
-            for (app, apis) in api_update.iter() {
-                for (api, count) in apis.iter() {
-                    update_count(app, api, count).await;
+        // Add record
+        for (app, apis) in wait_record.iter() {
+            for (api, times) in apis.iter() {
+                for (time, count) in times.iter() {
+                    add_rec(app, api, time, count).await;
                 }
             }
+        }
+    }
+
+    // 写入新生成的盐, 需要在草图之前写入
+    //
+    // Write the newly generated salt, must be written before the sketches
+    if let Some((day, salt)) = context!().visitors.get_salt_dirty() {
+        set_salt(day, &salt).await;
+    }
+
+    // 写入有改动的访客草图
+    //
+    // Write the changed visitor sketches
+    for (app, api, day, sketch) in context!().visitors.get_dirty(util::now()) {
+        set_visitor(&app, &api, day, sketch.as_bytes()).await;
+    }
 
-            // 添加记录
-            //
-            // Add record
-            for (app, apis) in wait_record.iter() {
-                for (api, times) in apis.iter() {
-                    for (time, count) in times.iter() {
-                        add_rec(app, api, time, count).await;
-                    }
+    // 写入有改动的标签组合
+    //
+    // Write the changed label combinations
+    for (app, api, labels, count) in context!().labels.get_dirty() {
+        set_label(&app, &api, &labels, count).await;
+    }
+
+    // 写入有改动的直方图
+    //
+    // Write the changed histograms
+    for (app, api, time, histogram) in context!().histograms.get_dirty(util::now()) {
+        let data = serde_json::to_string(&histogram).unwrap();
+        set_histogram(&app, &api, time, &data).await;
+    }
+
+    // 写入仪表的当前值与采样
+    //
+    // Write the current values and samples of the gauges
+    let (values, samples) = context!().gauges.get_dirty(util::now());
+    for (app, api, value) in values {
+        set_gauge(&app, &api, value).await;
+    }
+    for (app, api, time, bucket) in samples {
+        add_gauge_sample(&GaugeSample {
+            app,
+            api,
+            time,
+            min: bucket.min,
+            max: bucket.max,
+            sum: bucket.sum,
+            count: bucket.count,
+            last: bucket.last,
+        })
+        .await;
+    }
+
+    // 写入以 key 记录的计数
+    //
+    // Write the counters keyed by keys
+    for (key, count) in context!().counts.get_wait() {
+        set_count(&key, count).await;
+    }
+
+    // 写入键值存储中改动的值
+    //
+    // Write the changed values in the key-value store
+    for kind in Kind::ALL {
+        for (key, entry) in context!().kv.store(kind).get_wait() {
+            match entry {
+                Some(entry) => {
+                    set_kv(&Kv {
+                        kind: kind.as_str().to_owned(),
+                        key,
+                        value: entry.value.to_string(),
+                        version: entry.version,
+                        expires: entry.expires,
+                    })
+                    .await
                 }
+                None => delete_kv(kind.as_str(), &key).await,
             }
         }
     }
+
+    // 写入新的幂等键
+    //
+    // Write the new idempotency keys
+    for idempotency in context!().idempotency.get_wait() {
+        set_idempotency(&idempotency).await;
+    }
+
+    // 数据库写入失败时会中止, 不会记录, 就绪检查因此会失败
+    //
+    // A failed database write aborts before this, so the readiness check fails
+    *LAST_SYNC.lock() = Some((util::now(), started.elapsed()));
 }
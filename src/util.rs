@@ -14,3 +14,42 @@ pub fn is_valid(name: &str) -> bool {
     name.chars()
         .all(|c| c.is_ascii_alphanumeric() || c.eq(&'_') || c.eq(&'-') || c.eq(&'.') || c.eq(&'~'))
 }
+
+/// key 合法性检测, 字符与名称相同, 长度在 1 到 64 之间, 可以容纳 uuid
+///
+/// Key validity check, the same characters as names with a length between 1 and 64, long enough for uuids
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 64
+        && key.chars().all(|c| {
+            c.is_ascii_alphanumeric() || c.eq(&'_') || c.eq(&'-') || c.eq(&'.') || c.eq(&'~')
+        })
+}
+
+/// 回调地址合法性检测, 需要为带有主机的 http 或 https 地址
+///
+/// Webhook url validity check, must be an http or https url with a host
+pub fn is_valid_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
+
+/// 当前时间戳 (秒)
+///
+/// Current timestamp (seconds)
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// 当前时间戳 (秒, 带小数)
+///
+/// Current timestamp (seconds, fractional)
+pub fn now_f64() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{Server, Webhook};
+use serde_json::json;
+
+const ARGS: &[&str] = &["--alert-interval", "1", "--sync-interval", "1"];
+
+#[tokio::test]
+async fn fire_and_resolve() {
+    let server = Server::start(ARGS).await;
+    let webhook = Webhook::start(0).await;
+    server.add_apis("app1", &["api1"]).await;
+
+    let resp = server
+        .post(
+            "/alert",
+            json!({ "app": "app1", "api": "api1", "kind": "above", "threshold": 2, "window": 2, "webhook": webhook.url }),
+        )
+        .await;
+    assert_eq!(resp["code"], 0);
+    let id = resp["data"]["id"].as_i64().unwrap();
+
+    // 启动后的第一个完整窗口内不检查, 等待其结束后再调用
+    //
+    // Nothing is checked during the first full window after starting, so call after it ends
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    // 窗口内的调用超过阈值时触发
+    //
+    // Fires when the calls in the window exceed the threshold
+    for _ in 0..5 {
+        server.post("/api/app1/api1", json!({})).await;
+    }
+    let received = webhook.wait(1, Duration::from_secs(10)).await;
+    assert_eq!(received[0]["state"], "firing");
+    assert_eq!(received[0]["alert"]["id"], id);
+    assert!(received[0]["value"].as_i64().unwrap() > 2);
+    assert_eq!(
+        server.get(&format!("/alert/{id}")).await["data"]["firing"],
+        true
+    );
+
+    // 窗口内没有调用后恢复, 只发送一次
+    //
+    // Resolves once the window has no calls, and is sent only once
+    let received = webhook.wait(2, Duration::from_secs(10)).await;
+    assert_eq!(received[1]["state"], "resolved");
+    assert_eq!(received[1]["alert"]["firing"], false);
+    assert_eq!(
+        server.get(&format!("/alert/{id}")).await["data"]["firing"],
+        false
+    );
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(webhook.received().len(), 2);
+}
+
+#[tokio::test]
+async fn state_survives_restart() {
+    let server = Server::start(ARGS).await;
+    let webhook = Webhook::start(0).await;
+    server.add_apis("app1", &["api1"]).await;
+    server
+        .post(
+            "/alert",
+            json!({ "app": "app1", "kind": "total", "threshold": 1, "webhook": webhook.url }),
+        )
+        .await;
+    server.post("/api/app1/api1", json!({})).await;
+    let received = webhook.wait(1, Duration::from_secs(10)).await;
+    assert_eq!(received[0]["state"], "firing");
+
+    // 等待调用次数写入数据库, 否则重启后会恢复
+    //
+    // Wait for the count to be written to the database, otherwise it resolves after the restart
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // 重启后仍处于触发状态, 不会再次发送
+    //
+    // Still firing after a restart, so it is not sent again
+    let server = Server::start_in(server.stop(), ARGS).await;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(server.get("/alert").await["data"][0]["firing"], true);
+    assert_eq!(webhook.received().len(), 1);
+}
+
+#[tokio::test]
+async fn webhook_retry() {
+    let server = Server::start(ARGS).await;
+    let webhook = Webhook::start(2).await;
+    server.add_apis("app1", &["api1"]).await;
+    server
+        .post(
+            "/alert",
+            json!({ "app": "app1", "kind": "total", "threshold": 1, "webhook": webhook.url }),
+        )
+        .await;
+    server.post("/api/app1/api1", json!({})).await;
+
+    // 前两次返回 500, 重试使用相同的 id, 成功后不再发送
+    //
+    // The first two get a 500, retries use the same id and stop after a success
+    let received = webhook.wait(3, Duration::from_secs(15)).await;
+    assert_eq!(received.len(), 3);
+    assert!(received
+        .iter()
+        .all(|event| event["id"] == received[0]["id"]));
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert_eq!(webhook.received().len(), 3);
+}
+
+#[tokio::test]
+async fn unreachable_webhook_does_not_block() {
+    let server = Server::start(ARGS).await;
+    let webhook = Webhook::start(0).await;
+    server.add_apis("app1", &["api1", "api2"]).await;
+
+    // 一直失败的地址在退避重试时, 其他地址仍能收到
+    //
+    // While an always failing url is backing off, other urls still receive events
+    let failing = Webhook::start(usize::MAX).await;
+    for (api, url) in [("api1", &failing.url), ("api2", &webhook.url)] {
+        server
+            .post(
+                "/alert",
+                json!({ "app": "app1", "api": api, "kind": "total", "threshold": 1, "webhook": url }),
+            )
+            .await;
+    }
+    server.post("/api/app1/api1", json!({})).await;
+    failing.wait(1, Duration::from_secs(10)).await;
+    server.post("/api/app1/api2", json!({})).await;
+    let received = webhook.wait(1, Duration::from_secs(5)).await;
+    assert_eq!(received[0]["alert"]["api"], "api2");
+}
+
+#[tokio::test]
+async fn webhook_must_be_http() {
+    let server = Server::start(ARGS).await;
+    server.add_apis("app1", &[]).await;
+    for webhook in ["file:///etc/passwd", "not a url", "http://"] {
+        let resp = server
+            .post(
+                "/alert",
+                json!({ "app": "app1", "kind": "total", "threshold": 1, "webhook": webhook }),
+            )
+            .await;
+        assert_eq!(resp["code"], 1043, "{webhook}");
+    }
+    let resp = server
+        .post(
+            "/alert",
+            json!({ "app": "app1", "kind": "total", "threshold": 1, "webhook": "https://example.com/hook" }),
+        )
+        .await;
+    assert_eq!(resp["code"], 0);
+}
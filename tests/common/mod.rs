@@ -0,0 +1,227 @@
+#![allow(dead_code)]
+
+use std::{
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{http::StatusCode, routing::post, Json, Router};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+
+/// 独立的服务进程, 使用临时的数据目录与端口, 结束时关闭
+///
+/// A separate server process with a temporary data directory and port, killed when dropped
+pub struct Server {
+    child: Child,
+    pub dir: PathBuf,
+    pub port: u16,
+    client: reqwest::Client,
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// 新建一个空的临时目录
+///
+/// Create an empty temporary directory
+pub fn temp_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "apirec-test-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 以默认配置与额外的命令行参数运行服务, 不读取外部的 `APIREC_*` 环境变量
+///
+/// Run the server with the default configuration and extra arguments, outer `APIREC_*`
+/// environment variables are not read
+pub fn command(dir: &Path, args: &[&str]) -> Command {
+    let config = dir.join("config.toml");
+    if !config.exists() {
+        std::fs::write(&config, "").unwrap();
+    }
+    let mut command = Command::new(env!("CARGO_BIN_EXE_apirec"));
+    command
+        .arg("--config")
+        .arg(&config)
+        .arg("--data-dir")
+        .arg(dir.join("data"))
+        .args(["--log-target", "stdout", "--host", "127.0.0.1"])
+        .args(args)
+        .stdin(Stdio::null());
+    for (key, _) in std::env::vars() {
+        if key.starts_with("APIREC_") {
+            command.env_remove(key);
+        }
+    }
+    command
+}
+
+impl Server {
+    pub async fn start(args: &[&str]) -> Self {
+        Self::start_in(temp_dir(), args).await
+    }
+
+    /// 在指定目录中启动, 可用于重启后检查持久化的数据
+    ///
+    /// Start in the given directory, used to check persisted data after a restart
+    pub async fn start_in(dir: PathBuf, args: &[&str]) -> Self {
+        let port = free_port();
+        let child = command(&dir, args)
+            .args(["--port", &port.to_string()])
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let server = Self {
+            child,
+            dir,
+            port,
+            client: reqwest::Client::new(),
+        };
+        for _ in 0..100 {
+            if server
+                .client
+                .get(server.url("/healthz"))
+                .send()
+                .await
+                .is_ok()
+            {
+                return server;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("server did not start");
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, path)
+    }
+
+    pub async fn get(&self, path: &str) -> Value {
+        let resp = self.client.get(self.url(path)).send().await.unwrap();
+        resp.json().await.unwrap()
+    }
+
+    pub async fn post(&self, path: &str, body: Value) -> Value {
+        self.send(self.client.post(self.url(path)).json(&body))
+            .await
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> Value {
+        self.send(self.client.put(self.url(path)).json(&body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> Value {
+        self.send(self.client.delete(self.url(path))).await
+    }
+
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Value {
+        request.send().await.unwrap().json().await.unwrap()
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// 新增 app 与其下的 api
+    ///
+    /// Add an app and apis under it
+    pub async fn add_apis(&self, app: &str, apis: &[&str]) {
+        assert_eq!(self.post("/api", json!({ "app": app })).await["code"], 0);
+        for api in apis {
+            let resp = self
+                .post(&format!("/api/{app}"), json!({ "api": api }))
+                .await;
+            assert_eq!(resp["code"], 0);
+        }
+    }
+
+    /// 停止服务, 保留数据目录
+    ///
+    /// Stop the server, the data directory is kept
+    pub fn stop(mut self) -> PathBuf {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        std::mem::take(&mut self.dir)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if !self.dir.as_os_str().is_empty() {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// 本地的回调地址, 记录收到的内容, 前 `failures` 次请求返回 500
+///
+/// A local webhook recording the received bodies, the first `failures` requests get a 500
+pub struct Webhook {
+    pub url: String,
+    received: Arc<Mutex<Vec<Value>>>,
+}
+
+impl Webhook {
+    pub async fn start(failures: usize) -> Self {
+        let received = Arc::new(Mutex::new(vec![]));
+        let remaining = Arc::new(AtomicUsize::new(failures));
+        let app = Router::new().route(
+            "/",
+            post({
+                let received = received.clone();
+                move |Json(body): Json<Value>| async move {
+                    received.lock().push(body);
+                    let failed = remaining
+                        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                        .is_ok();
+                    match failed {
+                        true => StatusCode::INTERNAL_SERVER_ERROR,
+                        false => StatusCode::OK,
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Self { url, received }
+    }
+
+    /// 所有收到的请求, 包括返回 500 的请求
+    ///
+    /// All received requests, including the ones answered with a 500
+    pub fn received(&self) -> Vec<Value> {
+        self.received.lock().clone()
+    }
+
+    /// 等待收到至少 `count` 个请求
+    ///
+    /// Wait until at least `count` requests are received
+    pub async fn wait(&self, count: usize, timeout: Duration) -> Vec<Value> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.received.lock().len() < count && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        self.received()
+    }
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use std::{
+    net::TcpListener,
+    path::Path,
+    process::{Output, Stdio},
+    time::{Duration, Instant},
+};
+
+use common::{command, temp_dir};
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// 以给定的环境变量与参数运行, 等待退出并返回输出
+///
+/// Run with the given environment variables and arguments, wait for the exit and return the output
+fn run(dir: &Path, envs: &[(&str, &str)], args: &[&str]) -> Output {
+    command(dir, args)
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap()
+}
+
+/// 启动服务并返回其监听的端口, 候选端口中只有一个会被监听
+///
+/// Start the server and return the port it listens on, only one of the candidates is listened on
+fn listening(dir: &Path, envs: &[(&str, &str)], args: &[&str], candidates: &[u16]) -> u16 {
+    let mut child = command(dir, args)
+        .envs(envs.iter().copied())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let port = loop {
+        let found = candidates
+            .iter()
+            .copied()
+            .find(|port| std::net::TcpStream::connect(("127.0.0.1", *port)).is_ok());
+        if let Some(port) = found {
+            break port;
+        }
+        assert!(Instant::now() < deadline, "server did not start");
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    let _ = child.kill();
+    let _ = child.wait();
+    port
+}
+
+#[test]
+fn precedence() {
+    let dir = temp_dir();
+    let [file, env, arg] = [free_port(), free_port(), free_port()];
+    std::fs::write(dir.join("config.toml"), format!("port = {file}\n")).unwrap();
+    let env_port = env.to_string();
+    let arg_port = arg.to_string();
+    let envs = [("APIREC_PORT", env_port.as_str())];
+    let candidates = [file, env, arg];
+
+    // 命令行参数优先于环境变量, 环境变量优先于配置文件
+    //
+    // Arguments take precedence over environment variables, which take precedence over the file
+    assert_eq!(
+        listening(&dir, &envs, &["--port", &arg_port], &candidates),
+        arg
+    );
+    assert_eq!(listening(&dir, &envs, &[], &candidates), env);
+    assert_eq!(listening(&dir, &[], &[], &candidates), file);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn invalid_value_names_its_source() {
+    let dir = temp_dir();
+    let config = dir.join("config.toml");
+    std::fs::write(&config, "port = \"abc\"\n").unwrap();
+    let assert_source = |envs: &[(&str, &str)], args: &[&str], source: &str| {
+        let output = run(&dir, envs, args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "{stderr}");
+        assert!(
+            stderr.contains(&format!("invalid value for `port` from {source}:")),
+            "{stderr}"
+        );
+    };
+
+    // 错误指出生效的来源
+    //
+    // The error names the source that takes effect
+    let env = [("APIREC_PORT", "abc")];
+    assert_source(&[], &[], &config.display().to_string());
+    assert_source(&env, &[], "environment variable APIREC_PORT");
+    assert_source(&env, &["--port=abc"], "argument --port");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let dir = temp_dir();
+
+    // 拼错的命令行参数与子命令
+    //
+    // Misspelled arguments and subcommands
+    for (args, error) in [
+        (&["--prot", "9000"][..], "unknown argument `--prot`"),
+        (&["--replace"][..], "unknown argument `--replace`"),
+        (&["imprt", "dump.ndjson"][..], "unknown subcommand `imprt`"),
+    ] {
+        let output = run(&dir, &[], args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "{stderr}");
+        assert!(stderr.contains(error), "{stderr}");
+    }
+
+    // 配置文件中拼错的配置项
+    //
+    // A misspelled key in the configuration file
+    std::fs::write(dir.join("config.toml"), "prot = 9000\n").unwrap();
+    let output = run(&dir, &[], &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    assert!(stderr.contains("unknown key `prot` in"), "{stderr}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
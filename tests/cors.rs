@@ -0,0 +1,57 @@
+mod common;
+
+use common::Server;
+use reqwest::Method;
+
+const ORIGIN: &str = "https://page.example";
+
+/// 发送跨域请求, 返回响应中允许的来源
+///
+/// Send a cross-origin request and return the allowed origin of the response
+async fn allowed(server: &Server, method: Method, path: &str, preflight: Option<&str>) -> bool {
+    let mut request = server
+        .client()
+        .request(method, server.url(path))
+        .header("origin", ORIGIN);
+    if let Some(method) = preflight {
+        request = request.header("access-control-request-method", method);
+    }
+    let resp = request.send().await.unwrap();
+    resp.headers()
+        .get("access-control-allow-origin")
+        .is_some_and(|origin| origin == "*" || origin == ORIGIN)
+}
+
+#[tokio::test]
+async fn public_reads_and_admin_writes() {
+    let server = Server::start(&["--cors-origins", "[\"*\"]"]).await;
+
+    // 计数的读取与累加, 键值存储的读取与监听是公开的
+    //
+    // Reading and adding counters, reading and watching the key-value store are public
+    for path in [
+        "/count/likes",
+        "/bool/flag",
+        "/string/notice",
+        "/json/settings",
+    ] {
+        assert!(allowed(&server, Method::GET, path, None).await, "{path}");
+        let preflight = allowed(&server, Method::OPTIONS, path, Some("GET")).await;
+        assert!(preflight, "{path}");
+    }
+    assert!(allowed(&server, Method::GET, "/bool/flag/watch?timeout=0", None).await);
+    assert!(allowed(&server, Method::OPTIONS, "/count/likes", Some("POST")).await);
+    assert!(allowed(&server, Method::POST, "/count/likes", None).await);
+
+    // 写入键值存储与设置计数仍然是管理接口, 默认不允许跨域
+    //
+    // Writing the key-value store and setting counters are still admin, no cross-origin by default
+    assert!(!allowed(&server, Method::OPTIONS, "/count/likes", Some("PUT")).await);
+    for path in ["/bool/flag", "/string/notice", "/json/settings"] {
+        for method in ["POST", "DELETE"] {
+            let preflight = allowed(&server, Method::OPTIONS, path, Some(method)).await;
+            assert!(!preflight, "{method} {path}");
+        }
+    }
+    assert!(!allowed(&server, Method::OPTIONS, "/api", Some("POST")).await);
+}
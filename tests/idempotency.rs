@@ -0,0 +1,59 @@
+mod common;
+
+use std::time::Duration;
+
+use common::Server;
+use serde_json::Value;
+
+const ARGS: &[&str] = &["--sync-interval", "1", "--idempotency-persist", "true"];
+
+async fn hit(server: &Server, path: &str, key: &str) -> (bool, Value) {
+    let resp = server
+        .client()
+        .post(server.url(path))
+        .header("idempotency-key", key)
+        .send()
+        .await
+        .unwrap();
+    let replayed = resp.headers().get("idempotent-replayed").is_some();
+    (replayed, resp.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn replay() {
+    let server = Server::start(ARGS).await;
+    server.add_apis("app1", &["api1", "api2"]).await;
+
+    let (replayed, resp) = hit(&server, "/api/app1/api1", "k1").await;
+    assert!(!replayed);
+    assert_eq!(resp["data"], 1);
+
+    // 重复的请求返回原来的调用次数, 不再计数
+    //
+    // A duplicate request returns the original count and is not counted again
+    let (replayed, resp) = hit(&server, "/api/app1/api1", "k1").await;
+    assert!(replayed);
+    assert_eq!(resp["data"], 1);
+    let (_, resp) = hit(&server, "/api/app1/api1", "k2").await;
+    assert_eq!(resp["data"], 2);
+
+    // 幂等键不能用于同一个 app 的其他 api
+    //
+    // A key cannot be used with another api of the same app
+    let (replayed, resp) = hit(&server, "/api/app1/api2", "k1").await;
+    assert!(!replayed);
+    assert_eq!(resp["code"], 1047);
+    let (_, resp) = hit(&server, "/api/app1/api2", "k3").await;
+    assert_eq!(resp["data"], 1);
+
+    // 重启后仍然有效, 并保持与 api 的绑定
+    //
+    // Still valid after a restart, and still bound to the api
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let server = Server::start_in(server.stop(), ARGS).await;
+    let (replayed, resp) = hit(&server, "/api/app1/api1", "k1").await;
+    assert!(replayed);
+    assert_eq!(resp["data"], 1);
+    let (_, resp) = hit(&server, "/api/app1/api2", "k1").await;
+    assert_eq!(resp["code"], 1047);
+}
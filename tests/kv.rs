@@ -0,0 +1,99 @@
+mod common;
+
+use std::time::Duration;
+
+use common::Server;
+use serde_json::json;
+
+const ARGS: &[&str] = &["--sync-interval", "1"];
+
+#[tokio::test]
+async fn compare_and_set() {
+    let server = Server::start(ARGS).await;
+
+    let resp = server
+        .post("/string/key1", json!({ "data": "a", "version": 0 }))
+        .await;
+    let version = resp["data"]["version"].as_i64().unwrap();
+
+    // 版本不符时返回错误码与当前的值
+    //
+    // A version mismatch returns the error code and the current value
+    let resp = server
+        .post("/string/key1", json!({ "data": "b", "version": 0 }))
+        .await;
+    assert_eq!(resp["code"], 1033);
+    assert_eq!(resp["data"]["data"], "a");
+    assert_eq!(resp["data"]["version"], version);
+
+    let resp = server
+        .post("/string/key1", json!({ "data": "b", "version": version }))
+        .await;
+    assert_eq!(resp["code"], 0);
+    assert!(resp["data"]["version"].as_i64().unwrap() > version);
+
+    // 删除后旧的版本号不能覆盖新写入的值
+    //
+    // After a delete an old version cannot overwrite a newly written value
+    let old = resp["data"]["version"].clone();
+    server.delete("/string/key1").await;
+    server.post("/string/key1", json!({ "data": "c" })).await;
+    let resp = server
+        .post("/string/key1", json!({ "data": "d", "version": old }))
+        .await;
+    assert_eq!(resp["code"], 1033);
+
+    // 过期时间超出上限时拒绝
+    //
+    // A time to live above the maximum is rejected
+    let resp = server
+        .post("/string/key2", json!({ "data": "a", "ttl": i64::MAX }))
+        .await;
+    assert_eq!(resp["code"], 1031);
+
+    // 每种类型有独立的 key 空间, 值需要符合类型
+    //
+    // Every type has its own key space, and values must match the type
+    assert_eq!(server.get("/bool/key1").await["code"], 1032);
+    let resp = server.post("/bool/key1", json!({ "data": "yes" })).await;
+    assert_eq!(resp["code"], 1031);
+}
+
+#[tokio::test]
+async fn ttl_and_restart() {
+    let server = Server::start(ARGS).await;
+    server
+        .post("/json/short", json!({ "data": { "a": 1 }, "ttl": 1 }))
+        .await;
+    server
+        .post(
+            "/json/long",
+            json!({ "data": { "b": [1, 2] }, "ttl": 3600 }),
+        )
+        .await;
+    server.post("/bool/flag", json!({ "data": true })).await;
+    let version =
+        server.post("/bool/flag", json!({ "data": false })).await["data"]["version"].as_i64();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(server.get("/json/short").await["code"], 1032);
+    assert_eq!(server.delete("/json/short").await["code"], 1032);
+
+    // 重启后恢复未过期的值与版本
+    //
+    // Values that have not expired and their versions are restored after a restart
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let server = Server::start_in(server.stop(), ARGS).await;
+    assert_eq!(server.get("/json/short").await["code"], 1032);
+    let long = server.get("/json/long").await;
+    assert_eq!(long["data"]["data"], json!({ "b": [1, 2] }));
+    assert!(long["data"]["expires"].as_i64().is_some());
+    let flag = server.get("/bool/flag").await;
+    assert_eq!(
+        (
+            flag["data"]["data"].as_bool(),
+            flag["data"]["version"].as_i64()
+        ),
+        (Some(false), version)
+    );
+}
@@ -0,0 +1,58 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+};
+
+use common::{temp_dir, Server};
+
+/// 通过 Unix 套接字发送一次调用, 返回响应的原始内容
+///
+/// Send one call through the Unix socket and return the raw response
+fn post(socket: &Path, path: &str, user_agent: &str, body: &str) -> String {
+    let mut stream = UnixStream::connect(socket).unwrap();
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: localhost\r\nUser-Agent: {user_agent}\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    resp
+}
+
+#[tokio::test]
+async fn visitors_need_an_identifier() {
+    let dir = temp_dir();
+    let socket = dir.join("apirec.sock");
+    let server = Server::start_in(dir, &["--unix-socket", socket.to_str().unwrap()]).await;
+    server.add_apis("app1", &["api1"]).await;
+
+    // 没有 ip 时不从 user agent 推导访客, 调用仍然计数
+    //
+    // Without an ip no visitor is derived from the user agent, the calls are still counted
+    for user_agent in ["a", "b"] {
+        let resp = post(&socket, "/api/app1/api1", user_agent, "{}");
+        assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+    }
+    assert_eq!(server.get("/api/app1/api1").await["data"], 2);
+    assert_eq!(
+        server.get("/api/app1/api1/visitors").await["data"]["visitors"],
+        0
+    );
+
+    for visitor in ["user1", "user2", "user1"] {
+        let body = format!(r#"{{"visitor":"{visitor}"}}"#);
+        post(&socket, "/api/app1/api1", "a", &body);
+    }
+    assert_eq!(
+        server.get("/api/app1/api1/visitors").await["data"]["visitors"],
+        2
+    );
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::Server;
+use serde_json::json;
+
+const ARGS: &[&str] = &["--sync-interval", "1"];
+
+#[tokio::test]
+async fn wakes_on_change() {
+    let server = Server::start(ARGS).await;
+    server.post("/count/likes", json!({ "data": 1 })).await;
+    let first = server.get("/count/likes/watch").await;
+    assert_eq!(first["data"]["changed"], true);
+    assert_eq!(first["data"]["data"], 1);
+    let version = first["data"]["version"].as_u64().unwrap();
+
+    let path = format!("/count/likes/watch?version={version}&timeout=10");
+    let watch = server.get(&path);
+    let change = async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        server.post("/count/likes", json!({ "data": 2 })).await;
+    };
+    let start = Instant::now();
+    let (resp, _) = tokio::join!(watch, change);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(resp["data"]["changed"], true);
+    assert_eq!(resp["data"]["data"], 3);
+    assert!(resp["data"]["version"].as_u64().unwrap() > version);
+}
+
+#[tokio::test]
+async fn version_survives_eviction() {
+    let server = Server::start(ARGS).await;
+    server.post("/string/name", json!({ "data": "a" })).await;
+    let version = server.get("/string/name/watch").await["data"]["version"]
+        .as_u64()
+        .unwrap();
+
+    // 通道在同步时被移除, 其他值的改动不会使再次监听误报改动
+    //
+    // The channel is removed on sync, changes of other values do not make watching again report a change
+    server.post("/string/other", json!({ "data": "b" })).await;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    let resp = server
+        .get(&format!("/string/name/watch?version={version}&timeout=1"))
+        .await;
+    assert_eq!(resp["data"]["changed"], false);
+    assert_eq!(resp["data"]["version"], version);
+
+    // 通道被移除后的改动与删除仍会被报告
+    //
+    // Changes and deletions after the channel is removed are still reported
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    server.delete("/string/name").await;
+    let resp = server
+        .get(&format!("/string/name/watch?version={version}&timeout=1"))
+        .await;
+    assert_eq!(resp["data"]["changed"], true);
+    assert_eq!(resp["data"]["data"], serde_json::Value::Null);
+}